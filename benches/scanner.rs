@@ -0,0 +1,61 @@
+//! Scans generated sources of growing size. Each size is four times the one
+//! before and takes about five times as long to scan, where a scanner
+//! quadratic in the length of the source would take sixteen times as long.
+//! Throughput still drops somewhat as sources grow, most likely because the
+//! token vector outgrows the caches and gets reallocated more often.
+//!
+//! Run with `cargo bench --bench scanner`.
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+use rlox::scanner::Scanner;
+
+/// Covers every kind of token, with non-ASCII text in strings, comments and
+/// identifiers.
+const SNIPPET: &str = r#"// Greets everyone, 👋 included.
+class Greeter < Base {
+  init(name) {
+    this.name = name;
+  }
+
+  greet() {
+    print "héllo, " + this.name + "!";
+    return super.greet();
+  }
+}
+
+var größe = 0;
+for (var i = 0; i <= 100 and größe != nil; i = i + 1) {
+  if (!(i >= 50) or i == 7) größe = größe + i * 2.5 / -1;
+}
+"#;
+
+fn source(size: usize) -> String {
+    SNIPPET.repeat(size.div_ceil(SNIPPET.len()))
+}
+
+fn scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(10);
+
+    for kib in [64, 256, 1024, 4096] {
+        let source = source(kib * 1024);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{}KiB", kib)),
+            &source,
+            |b, source| {
+                b.iter_batched(
+                    || source.clone(),
+                    |source| Scanner::new(source).scan_tokens(),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
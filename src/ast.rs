@@ -1,21 +1,71 @@
-use std::fmt;
+use std::{fmt, rc::Rc};
 
 use crate::token::Token;
 
 #[derive(Debug, Clone)]
 pub enum Stmt {
+    Block(Vec<Stmt>),
+    Class(ClassStmt),
+    Expression(Expr),
+    /// Shared with the functions created from it, so calling one doesn't copy
+    /// its body.
+    Function(Rc<FunctionStmt>),
+    If(IfStmt),
     Print(Expr),
+    Return(ReturnStmt),
     Var(VariableStmt),
-    Nop,
+    While(WhileStmt),
 }
 
 impl Stmt {
+    pub fn new_if(condition: Expr, then_branch: Stmt, else_branch: Option<Stmt>) -> Stmt {
+        Stmt::If(IfStmt {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: else_branch.map(Box::new),
+        })
+    }
+
     pub fn new_variable(name: Token, initializer: Expr) -> Stmt {
         Stmt::Var(VariableStmt {
             name,
             initializer: Box::new(initializer),
         })
     }
+
+    pub fn new_while(condition: Expr, body: Stmt) -> Stmt {
+        Stmt::While(WhileStmt {
+            condition: Box::new(condition),
+            body: Box::new(body),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassStmt {
+    pub name: Token,
+    pub superclass: Option<VariableExpr>,
+    pub methods: Vec<Rc<FunctionStmt>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionStmt {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IfStmt {
+    pub condition: Box<Expr>,
+    pub then_branch: Box<Stmt>,
+    pub else_branch: Option<Box<Stmt>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReturnStmt {
+    pub keyword: Token,
+    pub value: Option<Box<Expr>>,
 }
 
 #[derive(Debug, Clone)]
@@ -24,16 +74,37 @@ pub struct VariableStmt {
     pub initializer: Box<Expr>,
 }
 
+#[derive(Debug, Clone)]
+pub struct WhileStmt {
+    pub condition: Box<Expr>,
+    pub body: Box<Stmt>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Expr {
+    Assign(Assign),
     Binary(Binary),
+    Call(Call),
+    Get(Get),
     Grouping(Grouping),
     Literal(Literal),
+    Logical(Logical),
+    Set(Set),
+    Super(SuperExpr),
+    This(ThisExpr),
     Unary(Unary),
     Variable(VariableExpr),
 }
 
 impl Expr {
+    pub fn new_assign(name: Token, value: Expr) -> Expr {
+        Expr::Assign(Assign {
+            name,
+            value: Box::new(value),
+            depth: None,
+        })
+    }
+
     pub fn new_binary(left: Expr, operator: Token, right: Expr) -> Expr {
         Expr::Binary(Binary {
             left: Box::new(left),
@@ -42,12 +113,43 @@ impl Expr {
         })
     }
 
+    pub fn new_call(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Expr {
+        Expr::Call(Call {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        })
+    }
+
+    pub fn new_get(object: Expr, name: Token) -> Expr {
+        Expr::Get(Get {
+            object: Box::new(object),
+            name,
+        })
+    }
+
     pub fn new_grouping(expr: Expr) -> Expr {
         Expr::Grouping(Grouping {
             expression: Box::new(expr),
         })
     }
 
+    pub fn new_logical(left: Expr, operator: Token, right: Expr) -> Expr {
+        Expr::Logical(Logical {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        })
+    }
+
+    pub fn new_set(object: Expr, name: Token, value: Expr) -> Expr {
+        Expr::Set(Set {
+            object: Box::new(object),
+            name,
+            value: Box::new(value),
+        })
+    }
+
     pub fn new_unary(operator: Token, right: Expr) -> Expr {
         Expr::Unary(Unary {
             operator,
@@ -56,6 +158,13 @@ impl Expr {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct Assign {
+    pub name: Token,
+    pub value: Box<Expr>,
+    pub depth: Option<usize>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Binary {
     pub left: Box<Expr>,
@@ -63,11 +172,52 @@ pub struct Binary {
     pub right: Box<Expr>,
 }
 
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub callee: Box<Expr>,
+    /// Closing paren of the argument list, used to report call errors.
+    pub paren: Token,
+    pub arguments: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Get {
+    pub object: Box<Expr>,
+    pub name: Token,
+}
+
 #[derive(Debug, Clone)]
 pub struct Grouping {
     pub expression: Box<Expr>,
 }
 
+#[derive(Debug, Clone)]
+pub struct Logical {
+    pub left: Box<Expr>,
+    pub operator: Token,
+    pub right: Box<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Set {
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub value: Box<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SuperExpr {
+    pub keyword: Token,
+    pub method: Token,
+    pub depth: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ThisExpr {
+    pub keyword: Token,
+    pub depth: Option<usize>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Unary {
     pub operator: Token,
@@ -77,9 +227,14 @@ pub struct Unary {
 #[derive(Debug, Clone)]
 pub struct VariableExpr {
     pub name: Token,
+    /// Number of scopes between the use and the declaration, filled in by the
+    /// `Resolver`. `None` means the variable is global.
+    pub depth: Option<usize>,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+/// Constants that appear in the source code. See `Value` for what the
+/// interpreter works with at runtime.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
     Number(f64),
     String(String),
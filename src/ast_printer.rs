@@ -1,16 +1,104 @@
-use crate::ast::{Expr, Literal};
+use crate::ast::{Expr, FunctionStmt, Literal, Stmt};
 
 pub struct AstPrinter;
 
 impl AstPrinter {
+    pub fn print_stmt(stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Block(statements) => AstPrinter::print_list("block", statements),
+            Stmt::Class(class) => {
+                let mut out = format!("(class {}", class.name.lexeme);
+                if let Some(superclass) = &class.superclass {
+                    out.push_str(&format!(" < {}", superclass.name.lexeme));
+                }
+                for method in &class.methods {
+                    out.push(' ');
+                    out.push_str(&AstPrinter::print_function(method));
+                }
+                out.push(')');
+                out
+            }
+            Stmt::Expression(expr) => format!("(; {})", AstPrinter::print(expr)),
+            Stmt::Function(function) => AstPrinter::print_function(function),
+            Stmt::If(if_stmt) => match &if_stmt.else_branch {
+                Some(else_branch) => format!(
+                    "(if {} {} {})",
+                    AstPrinter::print(&if_stmt.condition),
+                    AstPrinter::print_stmt(&if_stmt.then_branch),
+                    AstPrinter::print_stmt(else_branch)
+                ),
+                None => format!(
+                    "(if {} {})",
+                    AstPrinter::print(&if_stmt.condition),
+                    AstPrinter::print_stmt(&if_stmt.then_branch)
+                ),
+            },
+            Stmt::Print(expr) => format!("(print {})", AstPrinter::print(expr)),
+            Stmt::Return(return_stmt) => match &return_stmt.value {
+                Some(value) => format!("(return {})", AstPrinter::print(value)),
+                None => "(return)".to_string(),
+            },
+            Stmt::Var(var) => format!(
+                "(var {} {})",
+                var.name.lexeme,
+                AstPrinter::print(&var.initializer)
+            ),
+            Stmt::While(while_stmt) => format!(
+                "(while {} {})",
+                AstPrinter::print(&while_stmt.condition),
+                AstPrinter::print_stmt(&while_stmt.body)
+            ),
+        }
+    }
+
+    fn print_function(function: &FunctionStmt) -> String {
+        let params: Vec<&str> = function
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect();
+
+        let name = format!("fun {} ({})", function.name.lexeme, params.join(" "));
+        AstPrinter::print_list(&name, &function.body)
+    }
+
+    fn print_list(name: &str, statements: &[Stmt]) -> String {
+        let mut out = format!("({}", name);
+        for stmt in statements {
+            out.push(' ');
+            out.push_str(&AstPrinter::print_stmt(stmt));
+        }
+        out.push(')');
+        out
+    }
+
     pub fn print(expr: &Expr) -> String {
         match expr {
+            Expr::Assign(assign) => format!(
+                "(= {} {})",
+                assign.name.lexeme,
+                AstPrinter::print(&assign.value)
+            ),
             Expr::Binary(binary) => format!(
                 "({} {} {})",
                 binary.operator.lexeme,
                 AstPrinter::print(&binary.left),
                 AstPrinter::print(&binary.right)
             ),
+            Expr::Call(call) => {
+                let mut out = format!("(call {}", AstPrinter::print(&call.callee));
+                for argument in &call.arguments {
+                    out.push(' ');
+                    out.push_str(&AstPrinter::print(argument));
+                }
+                out.push(')');
+                out
+            }
+            Expr::Get(get) => format!(
+                "(get {} {})",
+                AstPrinter::print(&get.object),
+                get.name.lexeme
+            ),
             Expr::Grouping(grouping) => {
                 format!("(group {})", AstPrinter::print(&grouping.expression))
             }
@@ -20,6 +108,20 @@ impl AstPrinter {
                 Literal::Bool(b) => b.to_string(),
                 Literal::Nil => "nil".to_string(),
             },
+            Expr::Logical(logical) => format!(
+                "({} {} {})",
+                logical.operator.lexeme,
+                AstPrinter::print(&logical.left),
+                AstPrinter::print(&logical.right)
+            ),
+            Expr::Set(set) => format!(
+                "(set {} {} {})",
+                AstPrinter::print(&set.object),
+                set.name.lexeme,
+                AstPrinter::print(&set.value)
+            ),
+            Expr::Super(super_expr) => format!("(super {})", super_expr.method.lexeme),
+            Expr::This(_) => "this".to_string(),
             Expr::Unary(unary) => {
                 format!(
                     "({} {})",
@@ -27,6 +129,7 @@ impl AstPrinter {
                     AstPrinter::print(&unary.right)
                 )
             }
+            Expr::Variable(variable) => variable.name.lexeme.clone(),
         }
     }
 }
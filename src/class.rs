@@ -0,0 +1,145 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    error_code::ErrorCode,
+    function::{Callable, LoxFunction},
+    interpreter::{Interpreter, InterpreterError},
+    token::Token,
+    value::Value,
+};
+
+pub struct LoxClass {
+    pub name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> LoxClass {
+        LoxClass {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    /// Looks up a method on the class itself, then up the superclass chain.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        if let Some(method) = self.methods.get(name) {
+            return Some(Rc::clone(method));
+        }
+
+        self.superclass
+            .as_ref()
+            .and_then(|superclass| superclass.find_method(name))
+    }
+
+    /// Names of all methods, including inherited ones.
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.methods.keys().cloned().collect();
+        if let Some(superclass) = &self.superclass {
+            names.extend(superclass.method_names());
+        }
+        names
+    }
+}
+
+// Implemented for `Rc`, because every instance keeps a handle to its class.
+impl Callable for Rc<LoxClass> {
+    fn arity(&self) -> usize {
+        self.find_method("init")
+            .map_or(0, |initializer| initializer.arity())
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, InterpreterError> {
+        let instance = Rc::new(LoxInstance::new(Rc::clone(self)));
+
+        if let Some(initializer) = self.find_method("init") {
+            initializer
+                .bind(Rc::clone(&instance))
+                .call(interpreter, arguments)?;
+        }
+
+        Ok(Value::Instance(instance))
+    }
+}
+
+impl fmt::Debug for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LoxClass({})", self.name)
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: RefCell<HashMap<String, Value>>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> LoxInstance {
+        LoxInstance {
+            class,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Looks up a field first, then falls back to a method bound to this
+    /// instance.
+    pub fn get(self: &Rc<Self>, name: &Token) -> Result<Value, InterpreterError> {
+        if let Some(value) = self.fields.borrow().get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match self.class.find_method(&name.lexeme) {
+            Some(method) => Ok(Value::Function(Rc::new(method.bind(Rc::clone(self))))),
+            None => Err(InterpreterError {
+                operator: name.clone(),
+                message: format!("Undefined property '{}'.", name.lexeme),
+                code: ErrorCode::UndefinedProperty,
+            }),
+        }
+    }
+
+    /// Reads a field without falling back to methods.
+    pub fn field(&self, name: &str) -> Option<Value> {
+        self.fields.borrow().get(name).cloned()
+    }
+
+    /// Names of all fields and methods that can be accessed on the instance.
+    pub fn property_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.fields.borrow().keys().cloned().collect();
+        names.extend(self.class.method_names());
+        names
+    }
+
+    pub fn set(&self, name: &Token, value: Value) {
+        self.fields.borrow_mut().insert(name.lexeme.clone(), value);
+    }
+}
+
+// Written by hand, because fields may reference the instance itself.
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LoxInstance({})", self.class.name)
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
@@ -0,0 +1,445 @@
+use std::fmt::Write;
+
+use crate::{
+    error_code::ErrorCode,
+    interpreter::InterpreterError,
+    parser::ParseError,
+    resolver::ResolveError,
+    scanner::{ScanError, ScanErrorKind},
+    token::{Span, Token, TokenType},
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+}
+
+impl Severity {
+    fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// An error from any stage of a run, with everything needed to point at the
+/// source it came from.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: ErrorCode,
+    pub message: String,
+    pub line: i32,
+    pub span: Span,
+    /// Text the span should cover. A runtime error in the REPL may come from
+    /// a function declared on an earlier line, then the current source has
+    /// something else at that span and the snippet is left out.
+    pub lexeme: Option<String>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: ErrorCode, message: impl Into<String>, line: i32, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            line,
+            span,
+            lexeme: None,
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    fn at_token(code: ErrorCode, message: impl Into<String>, token: &Token) -> Diagnostic {
+        Diagnostic {
+            lexeme: Some(token.lexeme.clone()),
+            ..Diagnostic::error(code, message, token.line, token.span)
+        }
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Diagnostic {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Renders the diagnostic like rustc does, with the source line and the
+    /// span underlined:
+    ///
+    /// ```text
+    /// error[E0102]: Invalid assignment target.
+    ///  --> main.lox:1:7
+    ///   |
+    /// 1 | a + b = c;
+    ///   |       ^
+    ///   = help: did you mean `==`?
+    /// ```
+    pub fn render(&self, file: &str, source: &str, color: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("{}{}{}", style, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let line_number = self.line.to_string();
+        let pad = " ".repeat(line_number.len());
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "{}{}",
+            paint(
+                RED,
+                &format!("{}[{}]", self.severity.label(), self.code.code())
+            ),
+            paint(BOLD, &format!(": {}", self.message))
+        );
+        let _ = writeln!(
+            out,
+            "{}{} {}:{}:{}",
+            pad,
+            paint(BLUE, "-->"),
+            file,
+            self.line,
+            self.span.column
+        );
+
+        if let Some((text, prefix, width)) = self.snippet(source) {
+            let gutter = paint(BLUE, "|");
+            // Tabs are kept as is, so the carets line up with the source.
+            let indent: String = prefix
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            let _ = writeln!(out, "{} {}", pad, gutter);
+            let _ = writeln!(out, "{} {} {}", paint(BLUE, &line_number), gutter, text);
+            let _ = writeln!(
+                out,
+                "{} {} {}{}",
+                pad,
+                gutter,
+                indent,
+                paint(RED, &"^".repeat(width))
+            );
+        }
+
+        let footer = |label: &str, text: &str| {
+            format!(
+                "{} {} {} {}\n",
+                pad,
+                paint(BLUE, "="),
+                paint(BOLD, label),
+                text
+            )
+        };
+        for note in &self.notes {
+            out.push_str(&footer("note:", note));
+        }
+        if let Some(help) = &self.help {
+            out.push_str(&footer("help:", help));
+        }
+
+        out
+    }
+
+    /// Renders the diagnostic as a single-line JSON object, for tools that
+    /// read errors instead of people. `column` counts characters, the span
+    /// is given both in bytes and in characters from the start of the
+    /// source. Character offsets are `null` when the span doesn't point into
+    /// `source`, like for a REPL runtime error in an earlier line's function.
+    pub fn to_json(&self, file: &str, source: &str) -> String {
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+        let char_offset = |byte: usize| match self.covered(source) {
+            Some(_) => source[..byte].chars().count().to_string(),
+            None => String::from("null"),
+        };
+
+        format!(
+            concat!(
+                "{{\"file\":{},\"line\":{},\"column\":{},",
+                "\"span\":{{\"byte_start\":{},\"byte_end\":{},",
+                "\"char_start\":{},\"char_end\":{}}},",
+                "\"severity\":{},\"code\":{},\"message\":{},",
+                "\"notes\":[{}],\"help\":{}}}"
+            ),
+            json_string(file),
+            self.line,
+            self.span.column,
+            self.span.start,
+            self.span.end,
+            char_offset(self.span.start),
+            char_offset(self.span.end),
+            json_string(self.severity.label()),
+            json_string(self.code.code()),
+            json_string(&self.message),
+            notes.join(","),
+            self.help
+                .as_deref()
+                .map_or(String::from("null"), json_string),
+        )
+    }
+
+    /// The source the span covers, unless the span doesn't belong to it.
+    fn covered<'s>(&self, source: &'s str) -> Option<&'s str> {
+        let covered = source.get(self.span.start..self.span.end)?;
+        match &self.lexeme {
+            Some(lexeme) if lexeme != covered => None,
+            _ => Some(covered),
+        }
+    }
+
+    /// The source line the span starts on, the part of it before the span
+    /// and how many carets to underline the span with. Spans over multiple
+    /// lines are only underlined up to the end of the first one.
+    fn snippet<'s>(&self, source: &'s str) -> Option<(&'s str, &'s str, usize)> {
+        let covered = self.covered(source)?;
+
+        let line_start = source[..self.span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[line_start..]
+            .find('\n')
+            .map_or(source.len(), |i| line_start + i);
+
+        let text = source[line_start..line_end].trim_end_matches('\r');
+        let prefix = &source[line_start..self.span.start];
+        let width = covered.lines().next().unwrap_or("").chars().count();
+
+        Some((text, prefix, width.max(1)))
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl From<ScanError> for Diagnostic {
+    fn from(err: ScanError) -> Self {
+        let code = match err.kind {
+            ScanErrorKind::UnexpectedCharacter(_) => ErrorCode::UnexpectedCharacter,
+            ScanErrorKind::UnterminatedString => ErrorCode::UnterminatedString,
+        };
+        let diagnostic = Diagnostic::error(code, err.to_string(), err.line, err.span);
+
+        match err.kind {
+            ScanErrorKind::UnexpectedCharacter('\'') => {
+                diagnostic.with_help("strings are written in double quotes, like \"this\"")
+            }
+            ScanErrorKind::UnexpectedCharacter(_) => diagnostic,
+            ScanErrorKind::UnterminatedString => diagnostic.with_note(
+                "strings can span multiple lines, so the rest of the input was read into it",
+            ),
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Self {
+        let mut diagnostic = Diagnostic::at_token(err.code, err.message, &err.token);
+        if err.token.token_type == TokenType::Eof {
+            diagnostic = diagnostic.with_note("the input ended here");
+        }
+
+        match err.code {
+            ErrorCode::InvalidAssignmentTarget => diagnostic.with_help("did you mean `==`?"),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<ResolveError> for Diagnostic {
+    fn from(err: ResolveError) -> Self {
+        Diagnostic::at_token(err.code, err.message, &err.token)
+    }
+}
+
+impl From<InterpreterError> for Diagnostic {
+    fn from(err: InterpreterError) -> Self {
+        Diagnostic::at_token(err.code, err.message, &err.operator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    /// Diagnostics for every scan and parse error in `source`.
+    fn diagnostics(source: &str) -> Vec<Diagnostic> {
+        let (tokens, scan_errors) = Scanner::new(source.to_string()).scan_tokens();
+        let mut diagnostics: Vec<Diagnostic> =
+            scan_errors.into_iter().map(Diagnostic::from).collect();
+        if let Err(errors) = Parser::new(tokens).parse() {
+            diagnostics.extend(errors.into_iter().map(Diagnostic::from));
+        }
+        diagnostics
+    }
+
+    fn render(source: &str) -> String {
+        diagnostics(source)
+            .iter()
+            .map(|diagnostic| diagnostic.render("main.lox", source, false))
+            .collect()
+    }
+
+    #[test]
+    fn renders_span_with_help() {
+        assert_eq!(
+            render("a + b = c;"),
+            "\
+error[E0102]: Invalid assignment target.
+ --> main.lox:1:7
+  |
+1 | a + b = c;
+  |       ^
+  = help: did you mean `==`?
+"
+        );
+    }
+
+    #[test]
+    fn underlines_characters_rather_than_bytes() {
+        let source = "var größe = 1;";
+        let (tokens, _) = Scanner::new(source.to_string()).scan_tokens();
+        let diagnostic =
+            Diagnostic::at_token(ErrorCode::AlreadyDeclared, "Already declared.", &tokens[1]);
+        assert_eq!(
+            diagnostic.render("main.lox", source, false),
+            "\
+error[E0201]: Already declared.
+ --> main.lox:1:5
+  |
+1 | var größe = 1;
+  |     ^^^^^
+"
+        );
+    }
+
+    #[test]
+    fn keeps_tabs_in_the_indent() {
+        assert_eq!(
+            render("{\n\tprint 1 +;\n}"),
+            "\
+error[E0101]: Expected expression
+ --> main.lox:2:11
+  |
+2 | \tprint 1 +;
+  | \t         ^
+"
+        );
+    }
+
+    #[test]
+    fn points_after_the_last_token_at_end_of_input() {
+        assert_eq!(
+            render("print 1\n\n// done\n"),
+            "\
+error[E0100]: Expect ';' after value.
+ --> main.lox:1:8
+  |
+1 | print 1
+  |        ^
+  = note: the input ended here
+"
+        );
+    }
+
+    #[test]
+    fn leaves_out_snippet_of_other_source() {
+        let (tokens, _) = Scanner::new("fun f() { return x; }".to_string()).scan_tokens();
+        let diagnostic =
+            Diagnostic::at_token(ErrorCode::UndefinedVariable, "Undefined.", &tokens[6]);
+        assert_eq!(
+            diagnostic.render("<repl>", "f();", false),
+            "\
+error[E0300]: Undefined.
+ --> <repl>:1:18
+"
+        );
+    }
+
+    #[test]
+    fn json_gives_spans_in_bytes_and_characters() {
+        let source = "var größe = \"héllo\" +;";
+        let json: Vec<String> = diagnostics(source)
+            .iter()
+            .map(|diagnostic| diagnostic.to_json("main.lox", source))
+            .collect();
+        assert_eq!(
+            json,
+            vec![concat!(
+                r#"{"file":"main.lox","line":1,"column":22,"#,
+                r#""span":{"byte_start":24,"byte_end":25,"char_start":21,"char_end":22},"#,
+                r#""severity":"error","code":"E0101","message":"Expected expression","#,
+                r#""notes":[],"help":null}"#
+            )]
+        );
+    }
+
+    #[test]
+    fn json_leaves_out_character_offsets_of_other_source() {
+        let (tokens, _) = Scanner::new("fun f() { return x; }".to_string()).scan_tokens();
+        let diagnostic =
+            Diagnostic::at_token(ErrorCode::UndefinedVariable, "Undefined.", &tokens[6]);
+        assert_eq!(
+            diagnostic.to_json("<repl>", "f();"),
+            concat!(
+                r#"{"file":"<repl>","line":1,"column":18,"#,
+                r#""span":{"byte_start":17,"byte_end":18,"char_start":null,"char_end":null},"#,
+                r#""severity":"error","code":"E0300","message":"Undefined.","#,
+                r#""notes":[],"help":null}"#
+            )
+        );
+    }
+
+    #[test]
+    fn json_escapes_strings() {
+        let span = Span {
+            start: 0,
+            end: 1,
+            column: 1,
+        };
+        let diagnostic =
+            Diagnostic::error(ErrorCode::UnexpectedCharacter, "say \"hi\" \\ bye", 1, span)
+                .with_note("line\nbreak\ttab\u{1}")
+                .with_help("use \"\\\"");
+        assert_eq!(
+            diagnostic.to_json("dir\\main.lox", "x"),
+            concat!(
+                r#"{"file":"dir\\main.lox","line":1,"column":1,"#,
+                r#""span":{"byte_start":0,"byte_end":1,"char_start":0,"char_end":1},"#,
+                r#""severity":"error","code":"E0001","message":"say \"hi\" \\ bye","#,
+                r#""notes":["line\nbreak\ttab\u0001"],"help":"use \"\\\""}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{error_code::ErrorCode, interpreter::InterpreterError, token::Token, value::Value};
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(&mut self, name: String, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, InterpreterError> {
+        if let Some(value) = self.values.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(InterpreterError {
+                operator: name.clone(),
+                message: format!("Undefined variable '{}'.", name.lexeme),
+                code: ErrorCode::UndefinedVariable,
+            }),
+        }
+    }
+
+    /// Walks `distance` environments up the chain.
+    fn ancestor(
+        environment: &Rc<RefCell<Environment>>,
+        distance: usize,
+    ) -> Rc<RefCell<Environment>> {
+        let mut environment = Rc::clone(environment);
+        for _ in 0..distance {
+            let enclosing = environment
+                .borrow()
+                .enclosing
+                .clone()
+                .expect("resolver computed a distance past the global scope");
+            environment = enclosing;
+        }
+
+        environment
+    }
+
+    /// Reads a variable from the environment `distance` scopes up, as computed
+    /// by the `Resolver`.
+    pub fn get_at(
+        environment: &Rc<RefCell<Environment>>,
+        distance: usize,
+        name: &Token,
+    ) -> Result<Value, InterpreterError> {
+        Environment::ancestor(environment, distance)
+            .borrow()
+            .get(name)
+    }
+
+    pub fn assign_at(
+        environment: &Rc<RefCell<Environment>>,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), InterpreterError> {
+        Environment::ancestor(environment, distance)
+            .borrow_mut()
+            .assign(name, value)
+    }
+
+    /// Lists variables defined directly in this environment, sorted by name.
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        let mut bindings: Vec<(String, Value)> = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        bindings.sort_by(|(a, _), (b, _)| a.cmp(b));
+        bindings
+    }
+
+    /// Reads a variable defined directly in this environment, without looking
+    /// into the enclosing ones.
+    pub fn get_local(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), InterpreterError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(InterpreterError {
+                operator: name.clone(),
+                message: format!("Undefined variable '{}'.", name.lexeme),
+                code: ErrorCode::UndefinedVariable,
+            }),
+        }
+    }
+}
@@ -0,0 +1,621 @@
+/// Declares `ErrorCode` together with the code of each variant, so the list
+/// used to look codes up can't miss one.
+macro_rules! error_codes {
+    ($($variant:ident => $code:literal,)*) => {
+        /// Stable identifier of every kind of diagnostic, so tools can filter
+        /// them without matching on messages. Codes are grouped by the stage
+        /// reporting them: `E00xx` scanner, `E01xx` parser, `E02xx` resolver
+        /// and `E03xx` interpreter. A code is never reused for a different
+        /// kind of error.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ErrorCode {
+            $($variant,)*
+        }
+
+        impl ErrorCode {
+            const ALL: &[ErrorCode] = &[$(ErrorCode::$variant,)*];
+
+            pub fn code(&self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => $code,)*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    UnexpectedCharacter => "E0001",
+    UnterminatedString => "E0002",
+
+    MissingSemicolon => "E0100",
+    ExpectedExpression => "E0101",
+    InvalidAssignmentTarget => "E0102",
+    TooManyParameters => "E0103",
+    TooManyArguments => "E0104",
+    InheritsFromItself => "E0105",
+    ExpectedName => "E0106",
+    MissingLeftParen => "E0107",
+    UnclosedParen => "E0108",
+    MissingLeftBrace => "E0109",
+    UnclosedBrace => "E0110",
+    MissingDotAfterSuper => "E0111",
+
+    ReadInOwnInitializer => "E0200",
+    AlreadyDeclared => "E0201",
+    TopLevelReturn => "E0202",
+    ReturnValueFromInitializer => "E0203",
+    ThisOutsideClass => "E0204",
+    SuperOutsideClass => "E0205",
+    SuperWithoutSuperclass => "E0206",
+
+    UndefinedVariable => "E0300",
+    UndefinedProperty => "E0301",
+    OperandsNotNumbers => "E0302",
+    OperandsNotNumbersOrStrings => "E0303",
+    OperandNotNumber => "E0304",
+    DivisionByZero => "E0305",
+    NotCallable => "E0306",
+    WrongArgumentCount => "E0307",
+    PropertyOfNonInstance => "E0308",
+    FieldOfNonInstance => "E0309",
+    SuperclassNotClass => "E0310",
+    StackOverflow => "E0311",
+}
+
+impl ErrorCode {
+    /// Looks a code up by its name, e.g. `E0001`.
+    pub fn from_code(code: &str) -> Option<ErrorCode> {
+        ErrorCode::ALL
+            .iter()
+            .copied()
+            .find(|error_code| error_code.code() == code)
+    }
+
+    /// Longer description with examples, printed by `rlox --explain`.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => {
+                "\
+The scanner found a character that isn't part of the Lox language.
+
+Erroneous code example:
+
+    var name = 'Lox';
+
+Strings are written in double quotes:
+
+    var name = \"Lox\";
+"
+            }
+            ErrorCode::UnterminatedString => {
+                "\
+A string literal was never closed.
+
+Erroneous code example:
+
+    print \"hello;
+
+Strings can span multiple lines, so everything up to the end of the source
+is read into the string. Close it with a double quote:
+
+    print \"hello\";
+"
+            }
+            ErrorCode::MissingSemicolon => {
+                "\
+A statement or a `for` clause isn't followed by a semicolon.
+
+Erroneous code example:
+
+    print 1 + 2
+
+Every statement ends with a semicolon:
+
+    print 1 + 2;
+"
+            }
+            ErrorCode::ExpectedExpression => {
+                "\
+The parser expected an expression, like a literal, a variable or a call, but
+found something else.
+
+Erroneous code example:
+
+    var sum = 1 + ;
+
+Both operands of a binary operator must be given:
+
+    var sum = 1 + 2;
+"
+            }
+            ErrorCode::InvalidAssignmentTarget => {
+                "\
+The left side of `=` is something that can't be assigned to. Only variables
+and fields of instances can be assigned.
+
+Erroneous code example:
+
+    if (a + b = c) print \"equal\";
+
+To compare values, use `==`:
+
+    if (a + b == c) print \"equal\";
+"
+            }
+            ErrorCode::TooManyParameters => {
+                "\
+A function or method was declared with more than 255 parameters.
+
+Erroneous code example:
+
+    fun f(p1, p2, p3, /* ... */ p256) {}
+
+Group related values into an instance and pass that instead.
+"
+            }
+            ErrorCode::TooManyArguments => {
+                "\
+A call was passed more than 255 arguments. Functions can't take more than
+255 parameters, so such a call can never succeed.
+
+Erroneous code example:
+
+    f(a1, a2, a3, /* ... */ a256);
+
+Group related values into an instance and pass that instead.
+"
+            }
+            ErrorCode::InheritsFromItself => {
+                "\
+A class names itself as its superclass.
+
+Erroneous code example:
+
+    class Oops < Oops {}
+
+A class can only inherit from another class:
+
+    class Base {}
+    class Derived < Base {}
+"
+            }
+            ErrorCode::ExpectedName => {
+                "\
+A name was expected, like the name of a variable, function, class,
+parameter or property, but something else was found. Keywords can't be used
+as names.
+
+Erroneous code example:
+
+    var class = \"math\";
+
+Pick a name that isn't a keyword:
+
+    var subject = \"math\";
+"
+            }
+            ErrorCode::MissingLeftParen => {
+                "\
+An opening parenthesis is missing after `if`, `while`, `for` or the name of
+a function being declared.
+
+Erroneous code example:
+
+    if done print \"done\";
+
+Conditions are written in parentheses:
+
+    if (done) print \"done\";
+"
+            }
+            ErrorCode::UnclosedParen => {
+                "\
+A parenthesis was opened, but the parser found something else where it
+should have been closed. This happens in groupings, calls, parameter lists
+and the headers of `if`, `while` and `for`.
+
+Erroneous code example:
+
+    print (1 + 2;
+
+Close the parenthesis:
+
+    print (1 + 2);
+"
+            }
+            ErrorCode::MissingLeftBrace => {
+                "\
+The body of a function, method or class doesn't start with an opening brace.
+
+Erroneous code example:
+
+    fun greet() print \"hello\";
+
+Bodies are always blocks:
+
+    fun greet() {
+        print \"hello\";
+    }
+"
+            }
+            ErrorCode::UnclosedBrace => {
+                "\
+A block or class body was opened, but never closed.
+
+Erroneous code example:
+
+    while (true) {
+        print \"again\";
+
+Close the block:
+
+    while (true) {
+        print \"again\";
+    }
+"
+            }
+            ErrorCode::MissingDotAfterSuper => {
+                "\
+`super` isn't followed by a dot. It can only be used to access a method of
+the superclass.
+
+Erroneous code example:
+
+    class Derived < Base {
+        init() {
+            super();
+        }
+    }
+
+Name the method to call:
+
+    class Derived < Base {
+        init() {
+            super.init();
+        }
+    }
+"
+            }
+            ErrorCode::ReadInOwnInitializer => {
+                "\
+A local variable is used in the expression that initializes it. At that
+point the variable isn't defined yet.
+
+Erroneous code example:
+
+    var a = 1;
+    {
+        var a = a + 1;
+    }
+
+Give the new variable a different name:
+
+    var a = 1;
+    {
+        var b = a + 1;
+    }
+"
+            }
+            ErrorCode::AlreadyDeclared => {
+                "\
+A local scope declares two variables with the same name. Only global
+variables can be redeclared.
+
+Erroneous code example:
+
+    fun f() {
+        var a = 1;
+        var a = 2;
+    }
+
+Assign to the existing variable instead:
+
+    fun f() {
+        var a = 1;
+        a = 2;
+    }
+"
+            }
+            ErrorCode::TopLevelReturn => {
+                "\
+A `return` statement appears outside of any function or method.
+
+Erroneous code example:
+
+    return 1;
+
+`return` can only be used inside of a function body:
+
+    fun one() {
+        return 1;
+    }
+"
+            }
+            ErrorCode::ReturnValueFromInitializer => {
+                "\
+An `init` method returns a value. Initializers always return the instance
+being created, an empty `return;` can still be used to leave one early.
+
+Erroneous code example:
+
+    class Point {
+        init(x) {
+            this.x = x;
+            return x;
+        }
+    }
+
+Remove the returned value:
+
+    class Point {
+        init(x) {
+            this.x = x;
+        }
+    }
+"
+            }
+            ErrorCode::ThisOutsideClass => {
+                "\
+`this` is used outside of a method, where there's no instance it could refer
+to.
+
+Erroneous code example:
+
+    fun name() {
+        return this.name;
+    }
+
+Move the function into a class:
+
+    class Person {
+        name() {
+            return this.name;
+        }
+    }
+"
+            }
+            ErrorCode::SuperOutsideClass => {
+                "\
+`super` is used outside of a method, where there's no superclass it could
+refer to.
+
+Erroneous code example:
+
+    super.init();
+
+`super` can only be used in the methods of a subclass.
+"
+            }
+            ErrorCode::SuperWithoutSuperclass => {
+                "\
+`super` is used in a class that doesn't inherit from another class.
+
+Erroneous code example:
+
+    class Point {
+        init() {
+            super.init();
+        }
+    }
+
+Either remove the call or declare a superclass:
+
+    class Point < Shape {
+        init() {
+            super.init();
+        }
+    }
+"
+            }
+            ErrorCode::UndefinedVariable => {
+                "\
+A variable is read or assigned, but it was never declared.
+
+Erroneous code example:
+
+    count = 1;
+
+Declare the variable with `var` first:
+
+    var count = 1;
+"
+            }
+            ErrorCode::UndefinedProperty => {
+                "\
+An instance has neither a field nor a method with the accessed name.
+
+Erroneous code example:
+
+    class Point {}
+    print Point().x;
+
+Set the field before reading it:
+
+    var point = Point();
+    point.x = 1;
+    print point.x;
+"
+            }
+            ErrorCode::OperandsNotNumbers => {
+                "\
+An arithmetic or comparison operator was applied to values that aren't
+numbers. `-`, `/`, `<`, `<=`, `>` and `>=` only work on numbers, `*` also
+repeats a string a number of times.
+
+Erroneous code example:
+
+    print \"10\" < 2;
+
+Compare numbers with numbers:
+
+    print 10 < 2;
+"
+            }
+            ErrorCode::OperandsNotNumbersOrStrings => {
+                "\
+`+` was applied to values it can't add. It adds numbers and concatenates
+strings, a number and a string are concatenated too.
+
+Erroneous code example:
+
+    print 1 + nil;
+
+Erroneous code example:
+
+    print true + \"!\";
+"
+            }
+            ErrorCode::OperandNotNumber => {
+                "\
+Unary `-` was applied to a value that isn't a number.
+
+Erroneous code example:
+
+    print -\"1\";
+
+Only numbers can be negated:
+
+    print -1;
+"
+            }
+            ErrorCode::DivisionByZero => {
+                "\
+A number was divided by zero.
+
+Erroneous code example:
+
+    var count = 0;
+    print 10 / count;
+
+Check the divisor before dividing:
+
+    if (count != 0) print 10 / count;
+"
+            }
+            ErrorCode::NotCallable => {
+                "\
+A value that isn't a function, method or class was called.
+
+Erroneous code example:
+
+    var greeting = \"hello\";
+    greeting();
+
+Only functions, methods and classes can be called:
+
+    fun greeting() {
+        print \"hello\";
+    }
+    greeting();
+"
+            }
+            ErrorCode::WrongArgumentCount => {
+                "\
+A function, method or class was called with a different number of arguments
+than it declares parameters. Calling a class passes the arguments to its
+`init` method.
+
+Erroneous code example:
+
+    fun add(a, b) {
+        return a + b;
+    }
+    add(1);
+
+Pass one argument per parameter:
+
+    add(1, 2);
+"
+            }
+            ErrorCode::PropertyOfNonInstance => {
+                "\
+A property was read from a value that isn't an instance. Only instances have
+fields and methods.
+
+Erroneous code example:
+
+    var text = \"hello\";
+    print text.length;
+"
+            }
+            ErrorCode::FieldOfNonInstance => {
+                "\
+A field was set on a value that isn't an instance. Only instances have
+fields.
+
+Erroneous code example:
+
+    var count = 1;
+    count.value = 2;
+"
+            }
+            ErrorCode::SuperclassNotClass => {
+                "\
+The superclass named in a class declaration isn't a class.
+
+Erroneous code example:
+
+    var Base = \"base\";
+    class Derived < Base {}
+
+Inherit from a class:
+
+    class Base {}
+    class Derived < Base {}
+"
+            }
+            ErrorCode::StackOverflow => {
+                "\
+Calls nested more than 1000 levels deep, usually because a function calls
+itself without ever reaching a case that returns.
+
+Erroneous code example:
+
+    fun countdown(n) {
+        print n;
+        countdown(n - 1);
+    }
+    countdown(3);
+
+Stop the recursion once it's done:
+
+    fun countdown(n) {
+        if (n < 0) return;
+        print n;
+        countdown(n - 1);
+    }
+    countdown(3);
+"
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_unique() {
+        for (i, error_code) in ErrorCode::ALL.iter().enumerate() {
+            for other in &ErrorCode::ALL[i + 1..] {
+                assert_ne!(
+                    error_code.code(),
+                    other.code(),
+                    "{:?} and {:?} share a code",
+                    error_code,
+                    other
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn looks_up_every_code() {
+        for &error_code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_code(error_code.code()), Some(error_code));
+        }
+        assert_eq!(ErrorCode::from_code("E9999"), None);
+    }
+}
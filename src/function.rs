@@ -0,0 +1,132 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    ast::FunctionStmt,
+    class::LoxInstance,
+    environment::Environment,
+    interpreter::{Interpreter, InterpreterError, Unwind},
+    value::Value,
+};
+
+pub trait Callable {
+    fn arity(&self) -> usize;
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, InterpreterError>;
+}
+
+/// A user-defined function together with the environment it was declared in.
+pub struct LoxFunction {
+    declaration: Rc<FunctionStmt>,
+    closure: Rc<RefCell<Environment>>,
+    /// Initializers always return `this`, even on an early `return;`.
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<FunctionStmt>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> LoxFunction {
+        LoxFunction {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Returns a copy of the method with `this` bound to the given instance.
+    pub fn bind(&self, instance: Rc<LoxInstance>) -> LoxFunction {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+        environment.define("this".into(), Value::Instance(instance));
+
+        LoxFunction::new(
+            Rc::clone(&self.declaration),
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+}
+
+impl Callable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, InterpreterError> {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(param.lexeme.clone(), argument);
+        }
+
+        let environment = Rc::new(RefCell::new(environment));
+        let value = match interpreter.execute_block(&self.declaration.body, environment) {
+            Ok(()) => Value::Nil,
+            Err(Unwind::Return(value)) => value,
+            Err(Unwind::Error(err)) => return Err(err),
+        };
+
+        if self.is_initializer {
+            return Ok(self
+                .closure
+                .borrow()
+                .get_local("this")
+                .expect("initializers are always bound to an instance"));
+        }
+
+        Ok(value)
+    }
+}
+
+// Written by hand, because the closure may contain the function itself.
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LoxFunction({})", self.declaration.name.lexeme)
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
+
+/// A function implemented in Rust and exposed to Lox as a global.
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: fn(Vec<Value>) -> Value,
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, InterpreterError> {
+        Ok((self.function)(arguments))
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
@@ -1,150 +1,488 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use crate::{
-    ast::{Binary, Expr, Literal, Stmt, Unary},
+    ast::{Binary, Call, ClassStmt, Expr, Get, Logical, Set, Stmt, SuperExpr, Unary},
+    class::LoxClass,
+    environment::Environment,
+    error_code::ErrorCode,
+    function::{Callable, LoxFunction, NativeFunction},
     token::{Token, TokenType},
+    value::Value,
 };
 
 pub struct InterpreterError {
     pub operator: Token,
     pub message: String,
+    pub code: ErrorCode,
+}
+
+/// Reasons for statement execution to stop early. `return` travels up the
+/// call stack the same way errors do, until a function call catches it.
+pub enum Unwind {
+    Error(InterpreterError),
+    Return(Value),
 }
 
-pub struct Interpreter;
+impl From<InterpreterError> for Unwind {
+    fn from(err: InterpreterError) -> Self {
+        Unwind::Error(err)
+    }
+}
+
+/// How deep calls may nest before the interpreter gives up, well before the
+/// Rust stack it runs on would overflow.
+const MAX_CALL_DEPTH: usize = 1000;
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    /// Number of calls currently running.
+    call_depth: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
 
 impl Interpreter {
-    pub fn interpret(&self, statements: Vec<Stmt>) -> Result<(), InterpreterError> {
+    pub fn new() -> Interpreter {
+        let mut globals = Environment::new();
+        globals.define(
+            "clock".into(),
+            Value::Native(Rc::new(NativeFunction {
+                name: "clock",
+                arity: 0,
+                function: clock,
+            })),
+        );
+
+        let globals = Rc::new(RefCell::new(globals));
+        Interpreter {
+            environment: Rc::clone(&globals),
+            globals,
+            call_depth: 0,
+        }
+    }
+
+    /// Global variables, which is everything the REPL has defined so far.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.globals.borrow().bindings()
+    }
+
+    /// Shared handle to the global environment, for tools that outlive a
+    /// single run, like REPL completion.
+    pub fn globals_env(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.globals)
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), InterpreterError> {
         for statement in statements {
-            self.execute(statement)?;
+            match self.execute(statement) {
+                Ok(()) => {}
+                Err(Unwind::Error(err)) => return Err(err),
+                // `return` outside of a function just stops the script.
+                Err(Unwind::Return(_)) => return Ok(()),
+            }
         }
 
         Ok(())
     }
 
-    pub fn execute(&self, statement: Stmt) -> Result<(), InterpreterError> {
+    pub fn execute(&mut self, statement: &Stmt) -> Result<(), Unwind> {
         match statement {
+            Stmt::Block(statements) => {
+                let environment = Environment::new_enclosed(Rc::clone(&self.environment));
+                self.execute_block(statements, Rc::new(RefCell::new(environment)))?;
+            }
+            Stmt::Class(class) => self.execute_class(class)?,
+            Stmt::Expression(expr) => {
+                self.evaluate(expr)?;
+            }
+            Stmt::Function(function) => {
+                let name = function.name.lexeme.clone();
+                let function =
+                    LoxFunction::new(Rc::clone(function), Rc::clone(&self.environment), false);
+                self.environment
+                    .borrow_mut()
+                    .define(name, Value::Function(Rc::new(function)));
+            }
+            Stmt::If(if_stmt) => {
+                let condition = self.evaluate(&if_stmt.condition)?;
+                if self.is_truthy(condition) {
+                    self.execute(&if_stmt.then_branch)?;
+                } else if let Some(else_branch) = &if_stmt.else_branch {
+                    self.execute(else_branch)?;
+                }
+            }
             Stmt::Print(expr) => println!("{}", self.evaluate(expr)?),
-            Stmt::Var(_) => todo!(),
-            Stmt::Nop => {}
+            Stmt::Return(return_stmt) => {
+                let value = match &return_stmt.value {
+                    Some(value) => self.evaluate(value)?,
+                    None => Value::Nil,
+                };
+
+                return Err(Unwind::Return(value));
+            }
+            Stmt::Var(var) => {
+                let value = self.evaluate(&var.initializer)?;
+                self.environment
+                    .borrow_mut()
+                    .define(var.name.lexeme.clone(), value);
+            }
+            Stmt::While(while_stmt) => loop {
+                let condition = self.evaluate(&while_stmt.condition)?;
+                if !self.is_truthy(condition) {
+                    break;
+                }
+
+                self.execute(&while_stmt.body)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    fn execute_class(&mut self, class: &ClassStmt) -> Result<(), InterpreterError> {
+        let superclass = match &class.superclass {
+            Some(superclass) => match self.look_up_variable(&superclass.name, superclass.depth)? {
+                Value::Class(superclass) => Some(superclass),
+                _ => {
+                    return Err(InterpreterError {
+                        operator: superclass.name.clone(),
+                        message: "Superclass must be a class.".into(),
+                        code: ErrorCode::SuperclassNotClass,
+                    });
+                }
+            },
+            None => None,
+        };
+
+        // Methods of a subclass close over an extra environment holding `super`.
+        let mut closure = Rc::clone(&self.environment);
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new_enclosed(closure);
+            environment.define("super".into(), Value::Class(Rc::clone(superclass)));
+            closure = Rc::new(RefCell::new(environment));
         }
 
+        let mut methods = HashMap::new();
+        for method in &class.methods {
+            let name = method.name.lexeme.clone();
+            let is_initializer = name == "init";
+            let function = LoxFunction::new(Rc::clone(method), Rc::clone(&closure), is_initializer);
+            methods.insert(name, Rc::new(function));
+        }
+
+        let name = class.name.lexeme.clone();
+        let class = LoxClass::new(name.clone(), superclass, methods);
+        self.environment
+            .borrow_mut()
+            .define(name, Value::Class(Rc::new(class)));
+
         Ok(())
     }
 
-    pub fn evaluate(&self, expr: Expr) -> Result<Literal, InterpreterError> {
+    /// Executes statements inside the given environment, restoring the previous
+    /// one afterwards, even if one of the statements fails.
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement));
+
+        self.environment = previous;
+        result
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, InterpreterError> {
         match expr {
+            Expr::Assign(assign) => {
+                let value = self.evaluate(&assign.value)?;
+                match assign.depth {
+                    Some(depth) => Environment::assign_at(
+                        &self.environment,
+                        depth,
+                        &assign.name,
+                        value.clone(),
+                    )?,
+                    None => self
+                        .globals
+                        .borrow_mut()
+                        .assign(&assign.name, value.clone())?,
+                }
+                Ok(value)
+            }
             Expr::Binary(binary) => self.eval_binary(binary),
-            Expr::Grouping(grouping) => self.evaluate(*grouping.expression),
-            Expr::Literal(literal) => Ok(literal),
+            Expr::Call(call) => self.eval_call(call),
+            Expr::Get(get) => self.eval_get(get),
+            Expr::Grouping(grouping) => self.evaluate(&grouping.expression),
+            Expr::Literal(literal) => Ok(literal.clone().into()),
+            Expr::Logical(logical) => self.eval_logical(logical),
+            Expr::Set(set) => self.eval_set(set),
+            Expr::Super(super_expr) => self.eval_super(super_expr),
+            Expr::This(this) => self.look_up_variable(&this.keyword, this.depth),
             Expr::Unary(unary) => self.eval_unary(unary),
-            Expr::Variable(_) => todo!(),
+            Expr::Variable(variable) => self.look_up_variable(&variable.name, variable.depth),
         }
     }
 
-    fn eval_binary(&self, binary: Binary) -> Result<Literal, InterpreterError> {
-        let left = self.evaluate(*binary.left)?;
-        let right = self.evaluate(*binary.right)?;
+    fn look_up_variable(
+        &self,
+        name: &Token,
+        depth: Option<usize>,
+    ) -> Result<Value, InterpreterError> {
+        match depth {
+            Some(depth) => Environment::get_at(&self.environment, depth, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn eval_binary(&mut self, binary: &Binary) -> Result<Value, InterpreterError> {
+        let left = self.evaluate(&binary.left)?;
+        let right = self.evaluate(&binary.right)?;
 
         match binary.operator.token_type {
             TokenType::Minus => match (left, right) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l - r)),
-                _ => self.make_binary_err(binary.operator),
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l - r)),
+                _ => self.make_binary_err(&binary.operator),
             },
             TokenType::Slash => match (left, right) {
-                (Literal::Number(_), Literal::Number(0.0)) => Err(InterpreterError {
-                    operator: binary.operator,
+                (Value::Number(_), Value::Number(0.0)) => Err(InterpreterError {
+                    operator: binary.operator.clone(),
                     message: "Division by 0".into(),
+                    code: ErrorCode::DivisionByZero,
                 }),
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l / r)),
-                _ => self.make_binary_err(binary.operator),
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l / r)),
+                _ => self.make_binary_err(&binary.operator),
             },
             TokenType::Star => match (left, right) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l * r)),
-                (Literal::Number(l), Literal::String(r)) => {
-                    Ok(Literal::String(r.repeat(l as usize)))
-                }
-                (Literal::String(l), Literal::Number(r)) => {
-                    Ok(Literal::String(l.repeat(r as usize)))
-                }
-                _ => self.make_binary_err(binary.operator),
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l * r)),
+                (Value::Number(l), Value::String(r)) => Ok(Value::String(r.repeat(l as usize))),
+                (Value::String(l), Value::Number(r)) => Ok(Value::String(l.repeat(r as usize))),
+                _ => self.make_binary_err(&binary.operator),
             },
             TokenType::Plus => match (left, right) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l + r)),
-                (Literal::String(l), Literal::String(r)) => {
-                    Ok(Literal::String(format!("{}{}", l, r)))
-                }
-                (Literal::String(l), Literal::Number(r)) => {
-                    Ok(Literal::String(format!("{}{}", l, r)))
-                }
-                (Literal::Number(l), Literal::String(r)) => {
-                    Ok(Literal::String(format!("{}{}", l, r)))
-                }
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
+                (Value::String(l), Value::String(r)) => Ok(Value::String(format!("{}{}", l, r))),
+                (Value::String(l), Value::Number(r)) => Ok(Value::String(format!("{}{}", l, r))),
+                (Value::Number(l), Value::String(r)) => Ok(Value::String(format!("{}{}", l, r))),
                 _ => Err(InterpreterError {
-                    operator: binary.operator,
+                    operator: binary.operator.clone(),
                     message: "Both operands must be numbers or strings.".into(),
+                    code: ErrorCode::OperandsNotNumbersOrStrings,
                 }),
             },
             TokenType::Greater => match (left, right) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Bool(l > r)),
-                _ => self.make_binary_err(binary.operator),
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l > r)),
+                _ => self.make_binary_err(&binary.operator),
             },
             TokenType::GreaterEqual => match (left, right) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Bool(l >= r)),
-                _ => self.make_binary_err(binary.operator),
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l >= r)),
+                _ => self.make_binary_err(&binary.operator),
             },
             TokenType::Less => match (left, right) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Bool(l < r)),
-                _ => self.make_binary_err(binary.operator),
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l < r)),
+                _ => self.make_binary_err(&binary.operator),
             },
             TokenType::LessEqual => match (left, right) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Bool(l <= r)),
-                _ => self.make_binary_err(binary.operator),
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l <= r)),
+                _ => self.make_binary_err(&binary.operator),
             },
-            TokenType::BangEqual => Ok(Literal::Bool(!self.is_equal(left, right))),
-            TokenType::EqualEqual => Ok(Literal::Bool(self.is_equal(left, right))),
+            TokenType::BangEqual => Ok(Value::Bool(!self.is_equal(left, right))),
+            TokenType::EqualEqual => Ok(Value::Bool(self.is_equal(left, right))),
+
+            _ => Ok(Value::Nil),
+        }
+    }
+
+    fn eval_call(&mut self, call: &Call) -> Result<Value, InterpreterError> {
+        let callee = self.evaluate(&call.callee)?;
+
+        let mut arguments = Vec::with_capacity(call.arguments.len());
+        for argument in &call.arguments {
+            arguments.push(self.evaluate(argument)?);
+        }
+
+        let function: &dyn Callable = match &callee {
+            Value::Function(function) => function.as_ref(),
+            Value::Native(native) => native.as_ref(),
+            Value::Class(class) => class,
+            _ => {
+                return Err(InterpreterError {
+                    operator: call.paren.clone(),
+                    message: "Can only call functions and classes.".into(),
+                    code: ErrorCode::NotCallable,
+                });
+            }
+        };
+
+        if arguments.len() != function.arity() {
+            return Err(InterpreterError {
+                operator: call.paren.clone(),
+                message: format!(
+                    "Expected {} arguments but got {}.",
+                    function.arity(),
+                    arguments.len()
+                ),
+                code: ErrorCode::WrongArgumentCount,
+            });
+        }
+
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(InterpreterError {
+                operator: call.paren.clone(),
+                message: "Stack overflow.".into(),
+                code: ErrorCode::StackOverflow,
+            });
+        }
+
+        self.call_depth += 1;
+        let result = function.call(self, arguments);
+        self.call_depth -= 1;
 
-            _ => Ok(Literal::Nil),
+        result
+    }
+
+    fn eval_get(&mut self, get: &Get) -> Result<Value, InterpreterError> {
+        match self.evaluate(&get.object)? {
+            Value::Instance(instance) => instance.get(&get.name),
+            _ => Err(InterpreterError {
+                operator: get.name.clone(),
+                message: "Only instances have properties.".into(),
+                code: ErrorCode::PropertyOfNonInstance,
+            }),
+        }
+    }
+
+    fn eval_set(&mut self, set: &Set) -> Result<Value, InterpreterError> {
+        let instance = match self.evaluate(&set.object)? {
+            Value::Instance(instance) => instance,
+            _ => {
+                return Err(InterpreterError {
+                    operator: set.name.clone(),
+                    message: "Only instances have fields.".into(),
+                    code: ErrorCode::FieldOfNonInstance,
+                });
+            }
+        };
+
+        let value = self.evaluate(&set.value)?;
+        instance.set(&set.name, value.clone());
+
+        Ok(value)
+    }
+
+    fn eval_super(&mut self, super_expr: &SuperExpr) -> Result<Value, InterpreterError> {
+        // The resolver only lets `super` through inside of a subclass, where
+        // it's always bound one scope above `this`.
+        let depth = super_expr
+            .depth
+            .expect("resolver binds 'super' inside of subclasses");
+        let superclass = match Environment::get_at(&self.environment, depth, &super_expr.keyword)? {
+            Value::Class(superclass) => superclass,
+            _ => unreachable!("'super' is always bound to a class"),
+        };
+
+        let this = Token::new(
+            TokenType::This,
+            "this".into(),
+            None,
+            super_expr.keyword.line,
+            super_expr.keyword.span,
+        );
+        let instance = match Environment::get_at(&self.environment, depth - 1, &this)? {
+            Value::Instance(instance) => instance,
+            _ => unreachable!("'this' is always bound to an instance"),
+        };
+
+        match superclass.find_method(&super_expr.method.lexeme) {
+            Some(method) => Ok(Value::Function(Rc::new(method.bind(instance)))),
+            None => Err(InterpreterError {
+                operator: super_expr.method.clone(),
+                message: format!("Undefined property '{}'.", super_expr.method.lexeme),
+                code: ErrorCode::UndefinedProperty,
+            }),
         }
     }
 
-    fn make_binary_err(&self, operator: Token) -> Result<Literal, InterpreterError> {
+    fn make_binary_err(&self, operator: &Token) -> Result<Value, InterpreterError> {
         Err(InterpreterError {
-            operator,
+            operator: operator.clone(),
             message: "Both operands must be numbers.".into(),
+            code: ErrorCode::OperandsNotNumbers,
         })
     }
 
-    fn eval_unary(&self, unary: Unary) -> Result<Literal, InterpreterError> {
-        let right = self.evaluate(*unary.right)?;
+    /// Short-circuits and returns the operand itself rather than a bool, so
+    /// `nil or "default"` evaluates to `"default"`.
+    fn eval_logical(&mut self, logical: &Logical) -> Result<Value, InterpreterError> {
+        let left = self.evaluate(&logical.left)?;
+
+        if logical.operator.token_type == TokenType::Or {
+            if self.is_truthy(left.clone()) {
+                return Ok(left);
+            }
+        } else if !self.is_truthy(left.clone()) {
+            return Ok(left);
+        }
+
+        self.evaluate(&logical.right)
+    }
+
+    fn eval_unary(&mut self, unary: &Unary) -> Result<Value, InterpreterError> {
+        let right = self.evaluate(&unary.right)?;
 
         if unary.operator.token_type == TokenType::Minus {
             return match right {
-                Literal::Number(n) => Ok(Literal::Number(-n)),
-                _ => self.make_unary_err(unary.operator),
+                Value::Number(n) => Ok(Value::Number(-n)),
+                _ => self.make_unary_err(&unary.operator),
             };
         }
 
         if unary.operator.token_type == TokenType::Bang {
-            return Ok(Literal::Bool(!self.is_truthy(right)));
+            return Ok(Value::Bool(!self.is_truthy(right)));
         }
 
-        self.make_unary_err(unary.operator)
+        self.make_unary_err(&unary.operator)
     }
 
-    fn make_unary_err(&self, operator: Token) -> Result<Literal, InterpreterError> {
+    fn make_unary_err(&self, operator: &Token) -> Result<Value, InterpreterError> {
         Err(InterpreterError {
             operator: operator.clone(),
             message: "Operand must be a number.".into(),
+            code: ErrorCode::OperandNotNumber,
         })
     }
 
-    fn is_equal(&self, left: Literal, right: Literal) -> bool {
+    fn is_equal(&self, left: Value, right: Value) -> bool {
         left == right
     }
 
-    fn is_truthy(&self, literal: Literal) -> bool {
-        match literal {
-            Literal::Bool(b) => b,
-            Literal::Nil => false,
+    fn is_truthy(&self, value: Value) -> bool {
+        match value {
+            Value::Bool(b) => b,
+            Value::Nil => false,
             _ => true,
         }
     }
 }
+
+fn clock(_arguments: Vec<Value>) -> Value {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    Value::Number(now.as_secs_f64())
+}
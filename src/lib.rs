@@ -0,0 +1,15 @@
+pub mod ast;
+pub mod ast_printer;
+pub mod class;
+pub mod diagnostic;
+pub mod environment;
+pub mod error_code;
+pub mod function;
+pub mod interpreter;
+pub mod parser;
+pub mod repl;
+pub mod resolver;
+pub mod runner;
+pub mod scanner;
+pub mod token;
+pub mod value;
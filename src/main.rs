@@ -1,124 +1,81 @@
-mod ast;
-mod ast_printer;
-mod interpreter;
-mod parser;
-mod scanner;
-mod token;
+use std::{env::args, path::Path, thread};
 
-use scanner::Scanner;
-use std::{
-    env::args,
-    fs,
-    io::{self, Write},
-    path::Path,
+use rlox::{
+    error_code::ErrorCode,
+    runner::{ErrorFormat, LoxRunner},
 };
 
-use crate::{
-    interpreter::Interpreter,
-    parser::Parser,
-    token::{Token, TokenType},
-};
+/// Lox calls recurse on the Rust stack, so the interpreter runs on a thread
+/// with room for `MAX_CALL_DEPTH` nested calls even in debug builds.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
 fn main() {
-    let args: Vec<String> = args().collect();
-    let mut runner = LoxRunner;
+    let cli = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(cli)
+        .expect("failed to spawn the interpreter thread");
 
-    match args.len() {
-        1 => runner.run_prompt(),
-        2 => runner.run_file(Path::new(&args[1])),
-        _ => {
-            println!("Usage: rlox [file]");
-            std::process::exit(64);
-        }
+    if cli.join().is_err() {
+        std::process::exit(101);
     }
 }
 
-#[derive(Default)]
-pub struct LoxRunner;
-
-impl LoxRunner {
-    fn run_file(&mut self, path: &Path) {
-        let contents = fs::read_to_string(path).unwrap_or_else(|err| {
-            panic!(
-                "failed to read file {:?}: {:?}",
-                path.file_name().unwrap(),
-                err
-            )
-        });
-
-        self.run(contents);
-    }
-
-    fn run_prompt(mut self) {
-        let mut input = String::new();
-
-        loop {
-            print!("> ");
-            io::stdout().flush().unwrap();
-
-            match io::stdin().read_line(&mut input) {
-                Ok(0) => {
-                    println!("got EOF, closing...");
-                    return;
-                }
-                Ok(_) => {
-                    self.run(input.trim().to_string());
-                    input.clear();
-                }
-                Err(err) => {
-                    println!("failed to read line: {}", err);
-                    return;
+fn cli() {
+    let mut error_format = ErrorFormat::Human;
+    let mut files = Vec::new();
+
+    let mut args = args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--explain" {
+            explain(args.next());
+        } else if let Some(code) = arg.strip_prefix("--explain=") {
+            explain(Some(code.to_string()));
+        } else if let Some(format) = arg.strip_prefix("--error-format=") {
+            error_format = match format {
+                "human" => ErrorFormat::Human,
+                "json" => ErrorFormat::Json,
+                other => {
+                    eprintln!("Unknown error format {:?}, expected human or json", other);
+                    std::process::exit(64);
                 }
-            }
+            };
+        } else if arg.starts_with("--") {
+            eprintln!("Unknown option {}", arg);
+            usage();
+        } else {
+            files.push(arg);
         }
     }
 
-    fn run(&mut self, source: String) {
-        let scanner = Scanner::new(source);
-        let interpreter = Interpreter;
+    let mut runner = LoxRunner::new(error_format);
 
-        let tokens = match scanner.scan_tokens() {
-            Ok(tokens) => tokens,
-            Err(err) => {
-                self.error(0, &err.0);
-                std::process::exit(65);
-            }
-        };
-
-        let statements = match Parser::new(tokens).parse() {
-            Ok(expr) => expr,
-            Err(err) => {
-                self.error_token(err.token, &err.message);
-                std::process::exit(65);
-            }
-        };
-
-        match interpreter.interpret(statements) {
-            Ok(_) => {}
-            Err(err) => {
-                self.error_token(err.operator, &err.message);
-                std::process::exit(70);
-            }
-        }
+    match files.as_slice() {
+        [] => runner.run_prompt(),
+        [file] => runner.run_file(Path::new(file)),
+        _ => usage(),
     }
+}
 
-    pub fn error(&mut self, line: i32, message: &str) {
-        self.report(line, "", message);
-    }
+fn usage() -> ! {
+    eprintln!("Usage: rlox [--error-format=human|json] [file]");
+    eprintln!("       rlox --explain <code>");
+    std::process::exit(64);
+}
 
-    pub fn error_token(&mut self, token: Token, message: &str) {
-        match token.token_type {
-            TokenType::Eof => self.report(token.line, " at end", message),
-            _ => {
-                let mut pos_str = String::from("at '");
-                pos_str.push_str(token.lexeme.as_str());
-                pos_str.push('\'');
-                self.report(token.line, &pos_str, message);
-            }
-        }
-    }
+/// Prints the long description of an error code, like `rustc --explain`.
+fn explain(code: Option<String>) -> ! {
+    let Some(code) = code else {
+        usage();
+    };
 
-    fn report(&mut self, line: i32, pos: &str, message: &str) {
-        println!("[line {}] Error {}: {}", line, pos, message);
+    match ErrorCode::from_code(&code.to_uppercase()) {
+        Some(error_code) => {
+            print!("{}", error_code.explanation());
+            std::process::exit(0);
+        }
+        None => {
+            eprintln!("{} is not a valid error code", code);
+            std::process::exit(64);
+        }
     }
 }
@@ -1,5 +1,10 @@
+use std::rc::Rc;
+
 use crate::{
-    ast::{Expr, Literal, Stmt, VariableExpr},
+    ast::{
+        ClassStmt, Expr, FunctionStmt, Literal, ReturnStmt, Stmt, SuperExpr, ThisExpr, VariableExpr,
+    },
+    error_code::ErrorCode,
     token::{Token, TokenType},
 };
 
@@ -7,43 +12,155 @@ use crate::{
 pub struct ParseError {
     pub token: Token,
     pub message: String,
+    pub code: ErrorCode,
 }
 
+const MAX_ARGUMENTS: usize = 255;
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    /// Errors the parser has recovered from, reported all at once by `parse`.
+    errors: Vec<ParseError>,
+    /// Lets the last expression statement omit its `;`, so `1 + 2` can be
+    /// typed into the REPL as is.
+    repl: bool,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+            repl: false,
+        }
+    }
+
+    pub fn new_repl(tokens: Vec<Token>) -> Parser {
+        Parser {
+            repl: true,
+            ..Parser::new(tokens)
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, ParseError> {
+    pub fn parse(mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
         let mut statements = Vec::new();
         while !self.is_end() {
-            statements.push(self.declaration()?);
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
         }
 
-        Ok(statements)
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(self.errors)
+        }
     }
 
-    fn declaration(&mut self) -> Result<Stmt, ParseError> {
-        let stmt = if self.match_type(&[TokenType::Var]) {
+    /// Parses a declaration, on error it's recorded and the parser skips to
+    /// the next statement.
+    fn declaration(&mut self) -> Option<Stmt> {
+        let stmt = if self.match_type(&[TokenType::Class]) {
+            self.class_declaration()
+        } else if self.match_type(&[TokenType::Fun]) {
+            self.function("function")
+                .map(|function| Stmt::Function(Rc::new(function)))
+        } else if self.match_type(&[TokenType::Var]) {
             self.var_declaration()
         } else {
             self.statement()
         };
 
         match stmt {
-            Ok(stmt) => Ok(stmt),
-            Err(_) => {
+            Ok(stmt) => Some(stmt),
+            Err(err) => {
+                // The scanner has already reported the error token, whatever
+                // the parser trips over right after it is the same mistake.
+                if !self.follows_scan_error() {
+                    self.errors.push(err);
+                }
                 self.synchronize();
-                Ok(Stmt::Nop)
+                None
             }
         }
     }
 
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(TokenType::Identifier, "Expect class name.")?;
+
+        let mut superclass = None;
+        if self.match_type(&[TokenType::Less]) {
+            let superclass_name = self.consume(TokenType::Identifier, "Expect superclass name.")?;
+            if superclass_name.lexeme == name.lexeme {
+                self.error(
+                    superclass_name.clone(),
+                    ErrorCode::InheritsFromItself,
+                    "A class can't inherit from itself.",
+                );
+            }
+
+            superclass = Some(VariableExpr {
+                name: superclass_name,
+                depth: None,
+            });
+        }
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check_token(TokenType::RightBrace) && !self.is_end() {
+            methods.push(Rc::new(self.function("method")?));
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(Stmt::Class(ClassStmt {
+            name,
+            superclass,
+            methods,
+        }))
+    }
+
+    /// Parses a function or a method, `kind` is used for error messages only.
+    fn function(&mut self, kind: &str) -> Result<FunctionStmt, ParseError> {
+        let name = self.consume(TokenType::Identifier, &format!("Expect {} name.", kind))?;
+
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {} name.", kind),
+        )?;
+        let mut params = Vec::new();
+        if !self.check_token(TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    let token = self.peek();
+                    self.error(
+                        token,
+                        ErrorCode::TooManyParameters,
+                        &format!("Can't have more than {} parameters.", MAX_ARGUMENTS),
+                    );
+                }
+
+                params.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
+
+                if !self.match_type(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+        let body = self.block()?;
+
+        Ok(FunctionStmt { name, params, body })
+    }
+
     fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
         let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
 
@@ -61,13 +178,84 @@ impl Parser {
     }
 
     fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_type(&[TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.match_type(&[TokenType::If]) {
+            return self.if_statement();
+        }
         if self.match_type(&[TokenType::Print]) {
             return self.print_statement();
         }
+        if self.match_type(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.match_type(&[TokenType::LeftBrace]) {
+            return Ok(Stmt::Block(self.block()?));
+        }
+        if self.match_type(&[TokenType::While]) {
+            return self.while_statement();
+        }
 
         self.expression_statement()
     }
 
+    /// Parses a `for` loop and desugars it into a `while` loop, wrapped in
+    /// blocks for the initializer and the increment.
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.match_type(&[TokenType::Semicolon]) {
+            None
+        } else if self.match_type(&[TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            let expr = self.expression()?;
+            self.consume(TokenType::Semicolon, "Expect ';' after loop initializer.")?;
+            Some(Stmt::Expression(expr))
+        };
+
+        let mut condition = Expr::Literal(Literal::Bool(true));
+        if !self.check_token(TokenType::Semicolon) {
+            condition = self.expression()?;
+        }
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let mut increment = None;
+        if !self.check_token(TokenType::RightParen) {
+            increment = Some(self.expression()?);
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let mut body = self.statement()?;
+
+        if let Some(increment) = increment {
+            body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
+        }
+
+        body = Stmt::new_while(condition, body);
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = self.statement()?;
+        let mut else_branch = None;
+        if self.match_type(&[TokenType::Else]) {
+            else_branch = Some(self.statement()?);
+        }
+
+        Ok(Stmt::new_if(condition, then_branch, else_branch))
+    }
+
     fn print_statement(&mut self) -> Result<Stmt, ParseError> {
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
@@ -75,15 +263,98 @@ impl Parser {
         Ok(Stmt::Print(expr))
     }
 
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+
+        let mut value = None;
+        if !self.check_token(TokenType::Semicolon) {
+            value = Some(Box::new(self.expression()?));
+        }
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+
+        Ok(Stmt::Return(ReturnStmt { keyword, value }))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = self.statement()?;
+
+        Ok(Stmt::new_while(condition, body))
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+
+        while !self.check_token(TokenType::RightBrace) && !self.is_end() {
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+
+        Ok(statements)
+    }
+
     fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
         let expr = self.expression()?;
-        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        if !(self.repl && self.is_end()) {
+            self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        }
 
-        Ok(Stmt::Print(expr))
+        Ok(Stmt::Expression(expr))
     }
 
     fn expression(&mut self) -> Result<Expr, ParseError> {
-        self.equality()
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.or()?;
+
+        if self.match_type(&[TokenType::Equal]) {
+            let equals = self.previous();
+            let value = self.assignment()?;
+
+            match expr {
+                Expr::Variable(variable) => return Ok(Expr::new_assign(variable.name, value)),
+                Expr::Get(get) => return Ok(Expr::new_set(*get.object, get.name, value)),
+                // The parser isn't confused here, so report without unwinding.
+                _ => self.error(
+                    equals,
+                    ErrorCode::InvalidAssignmentTarget,
+                    "Invalid assignment target.",
+                ),
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+
+        while self.match_type(&[TokenType::Or]) {
+            let operator = self.previous();
+            let right = self.and()?;
+            expr = Expr::new_logical(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.equality()?;
+
+        while self.match_type(&[TokenType::And]) {
+            let operator = self.previous();
+            let right = self.equality()?;
+            expr = Expr::new_logical(expr, operator, right);
+        }
+
+        Ok(expr)
     }
 
     fn equality(&mut self) -> Result<Expr, ParseError> {
@@ -146,7 +417,51 @@ impl Parser {
             return Ok(Expr::new_unary(operator, right));
         }
 
-        self.primary()
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_type(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_type(&[TokenType::Dot]) {
+                let name =
+                    self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
+                expr = Expr::new_get(expr, name);
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut arguments = Vec::new();
+        if !self.check_token(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    let token = self.peek();
+                    self.error(
+                        token,
+                        ErrorCode::TooManyArguments,
+                        &format!("Can't have more than {} arguments.", MAX_ARGUMENTS),
+                    );
+                }
+
+                arguments.push(self.expression()?);
+
+                if !self.match_type(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+
+        Ok(Expr::new_call(callee, paren, arguments))
     }
 
     fn primary(&mut self) -> Result<Expr, ParseError> {
@@ -165,12 +480,37 @@ impl Parser {
             return Ok(Expr::Literal(prev.unwrap()));
         }
 
+        if self.match_type(&[TokenType::Super]) {
+            let keyword = self.previous();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self.consume(TokenType::Identifier, "Expect superclass method name.")?;
+            return Ok(Expr::Super(SuperExpr {
+                keyword,
+                method,
+                depth: None,
+            }));
+        }
+
+        if self.match_type(&[TokenType::This]) {
+            return Ok(Expr::This(ThisExpr {
+                keyword: self.previous(),
+                depth: None,
+            }));
+        }
+
         if self.match_type(&[TokenType::Identifier]) {
             return Ok(Expr::Variable(VariableExpr {
                 name: self.previous(),
+                depth: None,
             }));
         }
 
+        // Stands in for whatever the scanner couldn't make sense of, it has
+        // already been reported.
+        if self.match_type(&[TokenType::Error]) {
+            return Ok(Expr::Literal(Literal::Nil));
+        }
+
         if self.match_type(&[TokenType::LeftParen]) {
             let expr = self.expression()?;
             self.consume(TokenType::RightParen, "Expect ')' after expression")?;
@@ -180,6 +520,7 @@ impl Parser {
         Err(ParseError {
             token: self.peek(),
             message: String::from("Expected expression"),
+            code: ErrorCode::ExpectedExpression,
         })
     }
 
@@ -199,12 +540,22 @@ impl Parser {
             return Err(ParseError {
                 token: self.peek(),
                 message: message.to_string(),
+                code: missing_token_code(token_type),
             });
         };
 
         Ok(self.advance())
     }
 
+    /// Records an error the parser doesn't need to recover from.
+    fn error(&mut self, token: Token, code: ErrorCode, message: &str) {
+        self.errors.push(ParseError {
+            token,
+            message: message.to_string(),
+            code,
+        });
+    }
+
     fn synchronize(&mut self) {
         self.advance();
 
@@ -231,6 +582,13 @@ impl Parser {
         }
     }
 
+    /// Tells whether the current token is an error token or comes right
+    /// after one.
+    fn follows_scan_error(&self) -> bool {
+        self.peek().token_type == TokenType::Error
+            || (self.current > 0 && self.previous().token_type == TokenType::Error)
+    }
+
     fn check_token(&self, typ: TokenType) -> bool {
         if self.is_end() {
             return false;
@@ -259,3 +617,85 @@ impl Parser {
         self.tokens.get(self.current - 1).unwrap().clone()
     }
 }
+
+/// Error code for a token `Parser::consume` didn't find.
+fn missing_token_code(token_type: TokenType) -> ErrorCode {
+    match token_type {
+        TokenType::Semicolon => ErrorCode::MissingSemicolon,
+        TokenType::Identifier => ErrorCode::ExpectedName,
+        TokenType::LeftParen => ErrorCode::MissingLeftParen,
+        TokenType::RightParen => ErrorCode::UnclosedParen,
+        TokenType::LeftBrace => ErrorCode::MissingLeftBrace,
+        TokenType::RightBrace => ErrorCode::UnclosedBrace,
+        TokenType::Dot => ErrorCode::MissingDotAfterSuper,
+        _ => unreachable!("the parser never requires a {} token", token_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let (tokens, _) = Scanner::new(source.to_string()).scan_tokens();
+        Parser::new(tokens).parse()
+    }
+
+    fn errors(source: &str) -> Vec<(ErrorCode, i32)> {
+        parse(source)
+            .expect_err("source should fail to parse")
+            .iter()
+            .map(|err| (err.code, err.token.line))
+            .collect()
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let source = "print 1 +;\nvar = 2;\nprint (3;\nprint 4;\nfun f( {}";
+        assert_eq!(
+            errors(source),
+            vec![
+                (ErrorCode::ExpectedExpression, 1),
+                (ErrorCode::ExpectedName, 2),
+                (ErrorCode::UnclosedParen, 3),
+                (ErrorCode::ExpectedName, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_parsing_after_invalid_assignment_target() {
+        assert_eq!(
+            errors("a + b = c;\nprint;"),
+            vec![
+                (ErrorCode::InvalidAssignmentTarget, 1),
+                (ErrorCode::ExpectedExpression, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn recovers_inside_blocks() {
+        let source = "{\n  print 1\n  print 2;\n}\nwhile (true) {\n  var;\n}";
+        assert_eq!(
+            errors(source),
+            vec![
+                (ErrorCode::MissingSemicolon, 3),
+                (ErrorCode::ExpectedName, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_tokens_the_scanner_reported() {
+        assert!(parse("var x = @;\nprint # 1;\nprint \"abc;").is_ok());
+    }
+
+    #[test]
+    fn repl_allows_trailing_expression_without_semicolon() {
+        let (tokens, _) = Scanner::new("var a = 1; a + 1".to_string()).scan_tokens();
+        assert_eq!(Parser::new_repl(tokens).parse().unwrap().len(), 2);
+        assert_eq!(errors("a + 1"), vec![(ErrorCode::MissingSemicolon, 1)]);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{borrow::Cow, cell::RefCell, rc::Rc};
+
+use rustyline::{
+    Context, Helper,
+    completion::Completer,
+    highlight::{CmdKind, Highlighter},
+    hint::Hinter,
+    validate::Validator,
+};
+
+use crate::{
+    environment::Environment,
+    scanner::{KEYWORDS, Scanner},
+    token::TokenType,
+    value::Value,
+};
+
+const RESET: &str = "\x1b[0m";
+const KEYWORD: &str = "\x1b[35m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[33m";
+const OPERATOR: &str = "\x1b[36m";
+const COMMENT: &str = "\x1b[90m";
+const ERROR: &str = "\x1b[4;31m";
+
+/// Line editor helper for the REPL. Completes keywords, globals and the
+/// properties of instances, and highlights the input as it's typed.
+pub struct LoxHelper {
+    globals: Rc<RefCell<Environment>>,
+}
+
+impl LoxHelper {
+    pub fn new(globals: Rc<RefCell<Environment>>) -> LoxHelper {
+        LoxHelper { globals }
+    }
+
+    /// The interpreter may be replaced during the session, e.g. by `:reset`.
+    pub fn set_globals(&mut self, globals: Rc<RefCell<Environment>>) {
+        self.globals = globals;
+    }
+
+    /// Evaluates a dotted path like `a.b.c` by reading globals and fields.
+    /// Methods and getters are never called, completion has no side effects.
+    fn resolve_path(&self, path: &str) -> Option<Value> {
+        let mut names = path.split('.');
+        let mut value = self.globals.borrow().get_local(names.next()?)?;
+
+        for name in names {
+            value = match value {
+                Value::Instance(instance) => instance.field(name)?,
+                _ => return None,
+            };
+        }
+
+        Some(value)
+    }
+
+    fn property_candidates(&self, path: &str) -> Vec<String> {
+        match self.resolve_path(path) {
+            Some(Value::Instance(instance)) => instance.property_names(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn name_candidates(&self) -> Vec<String> {
+        let mut names: Vec<String> = KEYWORDS
+            .iter()
+            .map(|(keyword, _)| keyword.to_string())
+            .collect();
+        names.extend(
+            self.globals
+                .borrow()
+                .bindings()
+                .into_iter()
+                .map(|(name, _)| name),
+        );
+        names
+    }
+}
+
+impl Completer for LoxHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        let start = word_start(before);
+        let word = &before[start..];
+
+        let mut candidates = match before[..start].strip_suffix('.') {
+            Some(object) => self.property_candidates(&object[path_start(object)..]),
+            None => self.name_candidates(),
+        };
+
+        candidates.retain(|candidate| candidate.starts_with(word));
+        candidates.sort();
+        candidates.dedup();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for LoxHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LoxHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Cow::Owned(highlight(line))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, kind: CmdKind) -> bool {
+        kind != CmdKind::MoveCursor
+    }
+}
+
+impl Validator for LoxHelper {}
+
+impl Helper for LoxHelper {}
+
+/// Byte offset where the identifier ending at the end of `text` starts.
+fn word_start(text: &str) -> usize {
+    text.char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric())
+        .last()
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// Byte offset where the dotted path, like `a.b`, ending at the end of `text`
+/// starts.
+fn path_start(text: &str) -> usize {
+    text.char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '.')
+        .last()
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// Colors the source using the tokens the `Scanner` produces for it, with
+/// scan errors marked inline. Whatever lies between tokens is whitespace or
+/// a comment.
+fn highlight(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let (tokens, _) = Scanner::new(source.to_string()).scan_tokens();
+
+    let mut cursor = 0;
+    for token in tokens {
+        paint_gap(&mut out, &source[cursor..token.span.start]);
+        paint(
+            &mut out,
+            &source[token.span.start..token.span.end],
+            color(token.token_type),
+        );
+        cursor = token.span.end;
+    }
+    paint_gap(&mut out, &source[cursor..]);
+
+    out
+}
+
+/// Copies the text between two tokens, with comments colored.
+fn paint_gap(out: &mut String, gap: &str) {
+    let mut rest = gap;
+    while let Some(start) = rest.find("//") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find('\n').map_or(rest.len(), |i| start + i);
+        paint(out, &rest[start..end], Some(COMMENT));
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+}
+
+fn paint(out: &mut String, text: &str, color: Option<&str>) {
+    match color {
+        Some(color) => {
+            out.push_str(color);
+            out.push_str(text);
+            out.push_str(RESET);
+        }
+        None => out.push_str(text),
+    }
+}
+
+fn color(token_type: TokenType) -> Option<&'static str> {
+    if KEYWORDS.iter().any(|(_, keyword)| *keyword == token_type) {
+        return Some(KEYWORD);
+    }
+
+    match token_type {
+        TokenType::String => Some(STRING),
+        TokenType::Number => Some(NUMBER),
+        TokenType::Error => Some(ERROR),
+        TokenType::Identifier | TokenType::Eof | TokenType::Unknown => None,
+        _ => Some(OPERATOR),
+    }
+}
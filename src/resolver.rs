@@ -0,0 +1,445 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    ast::{ClassStmt, Expr, FunctionStmt, Stmt},
+    error_code::ErrorCode,
+    token::Token,
+};
+
+#[derive(Debug, Clone)]
+pub struct ResolveError {
+    pub token: Token,
+    pub message: String,
+    pub code: ErrorCode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Static pass that runs between parsing and interpreting. It binds every
+/// local variable use to the scope it was declared in and reports mistakes
+/// that can be caught without running the code.
+pub struct Resolver {
+    /// Local scopes only, globals are never tracked. The value tells whether
+    /// the variable's initializer has finished resolving.
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    errors: Vec<ResolveError>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Resolver::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Resolver {
+        Resolver {
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn resolve(mut self, statements: &mut [Stmt]) -> Result<(), Vec<ResolveError>> {
+        self.resolve_stmts(statements);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn resolve_stmts(&mut self, statements: &mut [Stmt]) {
+        for statement in statements {
+            self.resolve_stmt(statement);
+        }
+    }
+
+    fn resolve_stmt(&mut self, statement: &mut Stmt) {
+        match statement {
+            Stmt::Block(statements) => {
+                self.begin_scope();
+                self.resolve_stmts(statements);
+                self.end_scope();
+            }
+            Stmt::Class(class) => self.resolve_class(class),
+            Stmt::Expression(expr) | Stmt::Print(expr) => self.resolve_expr(expr),
+            Stmt::Function(function) => {
+                self.declare(&function.name);
+                self.define(&function.name);
+                // Nothing shares the declaration before it's resolved, so this
+                // never copies it.
+                self.resolve_function(Rc::make_mut(function), FunctionType::Function);
+            }
+            Stmt::If(if_stmt) => {
+                self.resolve_expr(&mut if_stmt.condition);
+                self.resolve_stmt(&mut if_stmt.then_branch);
+                if let Some(else_branch) = &mut if_stmt.else_branch {
+                    self.resolve_stmt(else_branch);
+                }
+            }
+            Stmt::Return(return_stmt) => {
+                if self.current_function == FunctionType::None {
+                    self.error(
+                        &return_stmt.keyword,
+                        ErrorCode::TopLevelReturn,
+                        "Can't return from top-level code.",
+                    );
+                }
+
+                if let Some(value) = &mut return_stmt.value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(
+                            &return_stmt.keyword,
+                            ErrorCode::ReturnValueFromInitializer,
+                            "Can't return a value from an initializer.",
+                        );
+                    }
+
+                    self.resolve_expr(value);
+                }
+            }
+            Stmt::Var(var) => {
+                self.declare(&var.name);
+                self.resolve_expr(&mut var.initializer);
+                self.define(&var.name);
+            }
+            Stmt::While(while_stmt) => {
+                self.resolve_expr(&mut while_stmt.condition);
+                self.resolve_stmt(&mut while_stmt.body);
+            }
+        }
+    }
+
+    fn resolve_class(&mut self, class: &mut ClassStmt) {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(&class.name);
+        self.define(&class.name);
+
+        if let Some(superclass) = &mut class.superclass {
+            self.current_class = ClassType::Subclass;
+            superclass.depth = self.resolve_local(&superclass.name);
+
+            self.begin_scope();
+            self.define_name("super");
+        }
+
+        self.begin_scope();
+        self.define_name("this");
+
+        for method in &mut class.methods {
+            let function_type = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+
+            self.resolve_function(Rc::make_mut(method), function_type);
+        }
+
+        self.end_scope();
+
+        if class.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+    }
+
+    fn resolve_function(&mut self, function: &mut FunctionStmt, function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_stmts(&mut function.body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+    }
+
+    fn resolve_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Assign(assign) => {
+                self.resolve_expr(&mut assign.value);
+                assign.depth = self.resolve_local(&assign.name);
+            }
+            Expr::Binary(binary) => {
+                self.resolve_expr(&mut binary.left);
+                self.resolve_expr(&mut binary.right);
+            }
+            Expr::Call(call) => {
+                self.resolve_expr(&mut call.callee);
+                for argument in &mut call.arguments {
+                    self.resolve_expr(argument);
+                }
+            }
+            Expr::Get(get) => self.resolve_expr(&mut get.object),
+            Expr::Grouping(grouping) => self.resolve_expr(&mut grouping.expression),
+            Expr::Literal(_) => {}
+            Expr::Logical(logical) => {
+                self.resolve_expr(&mut logical.left);
+                self.resolve_expr(&mut logical.right);
+            }
+            Expr::Set(set) => {
+                self.resolve_expr(&mut set.value);
+                self.resolve_expr(&mut set.object);
+            }
+            Expr::Super(super_expr) => {
+                match self.current_class {
+                    ClassType::None => self.error(
+                        &super_expr.keyword,
+                        ErrorCode::SuperOutsideClass,
+                        "Can't use 'super' outside of a class.",
+                    ),
+                    ClassType::Class => self.error(
+                        &super_expr.keyword,
+                        ErrorCode::SuperWithoutSuperclass,
+                        "Can't use 'super' in a class with no superclass.",
+                    ),
+                    ClassType::Subclass => {}
+                }
+
+                super_expr.depth = self.resolve_local(&super_expr.keyword);
+            }
+            Expr::This(this) => {
+                if self.current_class == ClassType::None {
+                    self.error(
+                        &this.keyword,
+                        ErrorCode::ThisOutsideClass,
+                        "Can't use 'this' outside of a class.",
+                    );
+                    return;
+                }
+
+                this.depth = self.resolve_local(&this.keyword);
+            }
+            Expr::Unary(unary) => self.resolve_expr(&mut unary.right),
+            Expr::Variable(variable) => {
+                let in_initializer = self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(&variable.name.lexeme))
+                    == Some(&false);
+                if in_initializer {
+                    self.error(
+                        &variable.name,
+                        ErrorCode::ReadInOwnInitializer,
+                        "Can't read local variable in its own initializer.",
+                    );
+                }
+
+                variable.depth = self.resolve_local(&variable.name);
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&name.lexeme) {
+            self.error(
+                name,
+                ErrorCode::AlreadyDeclared,
+                "Already a variable with this name in this scope.",
+            );
+            return;
+        }
+
+        scope.insert(name.lexeme.clone(), false);
+    }
+
+    fn define(&mut self, name: &Token) {
+        self.define_name(&name.lexeme);
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), true);
+        }
+    }
+
+    /// Returns how many scopes up the variable was declared, or `None` if it
+    /// wasn't found and must be a global.
+    fn resolve_local(&self, name: &Token) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.lexeme))
+    }
+
+    fn error(&mut self, token: &Token, code: ErrorCode, message: &str) {
+        self.errors.push(ResolveError {
+            token: token.clone(),
+            message: message.to_string(),
+            code,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::ReturnStmt, parser::Parser, scanner::Scanner};
+
+    fn resolve(source: &str) -> Result<Vec<Stmt>, Vec<ResolveError>> {
+        let (tokens, errors) = Scanner::new(source.to_string()).scan_tokens();
+        assert!(errors.is_empty());
+        let mut statements = Parser::new(tokens).parse().expect("source should parse");
+        Resolver::new()
+            .resolve(&mut statements)
+            .map(|()| statements)
+    }
+
+    /// The body of a function declaration statement.
+    fn body(stmt: &Stmt) -> &[Stmt] {
+        match stmt {
+            Stmt::Function(function) => &function.body,
+            other => panic!("expected a function declaration, got {:?}", other),
+        }
+    }
+
+    fn error_codes(source: &str) -> Vec<ErrorCode> {
+        resolve(source)
+            .expect_err("source should fail to resolve")
+            .iter()
+            .map(|err| err.code)
+            .collect()
+    }
+
+    #[test]
+    fn resolves_valid_program() {
+        let source = "
+            var a = 1;
+            var a = 2;
+            class Base { init(x) { this.x = x; return; } }
+            class Derived < Base { init() { super.init(a); } }
+            fun f() { var b = a; { var c = b; } return b; }
+        ";
+        assert!(resolve(source).is_ok());
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let source = "
+            return 1;
+            print this;
+            fun f() { var a = 1; var a = 2; }
+        ";
+        assert_eq!(
+            error_codes(source),
+            vec![
+                ErrorCode::TopLevelReturn,
+                ErrorCode::ThisOutsideClass,
+                ErrorCode::AlreadyDeclared,
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_local_read_in_own_initializer() {
+        let errors = resolve("{ var a = 1; { var a = a + 1; } }").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, ErrorCode::ReadInOwnInitializer);
+        assert_eq!(errors[0].token.span.start, 23);
+    }
+
+    #[test]
+    fn reports_misused_super_and_initializer_return() {
+        let source = "
+            super.f();
+            class A { init() { return 1; } f() { super.f(); } }
+        ";
+        assert_eq!(
+            error_codes(source),
+            vec![
+                ErrorCode::SuperOutsideClass,
+                ErrorCode::ReturnValueFromInitializer,
+                ErrorCode::SuperWithoutSuperclass,
+            ]
+        );
+    }
+
+    #[test]
+    fn closure_binds_to_the_scope_it_was_declared_in() {
+        let source = "
+            var a = \"global\";
+            {
+                fun showA() { print a; }
+                showA();
+                var a = \"block\";
+                showA();
+            }
+        ";
+        let statements = resolve(source).unwrap();
+
+        let Stmt::Block(block) = &statements[1] else {
+            panic!("expected a block, got {:?}", statements[1]);
+        };
+        let Stmt::Print(Expr::Variable(a)) = &body(&block[0])[0] else {
+            panic!("expected `print a;`");
+        };
+        assert_eq!(a.depth, None);
+    }
+
+    #[test]
+    fn captured_local_resolves_to_its_declaring_scope() {
+        let source = "
+            fun outer() {
+                var x = 1;
+                fun middle() {
+                    fun inner() { return x; }
+                    return x;
+                }
+            }
+        ";
+        let statements = resolve(source).unwrap();
+
+        let outer = body(&statements[0]);
+        let middle = body(&outer[1]);
+        let inner = body(&middle[0]);
+        let depth = |stmt: &Stmt| match stmt {
+            Stmt::Return(ReturnStmt {
+                value: Some(value), ..
+            }) => match value.as_ref() {
+                Expr::Variable(x) => x.depth,
+                other => panic!("expected `x`, got {:?}", other),
+            },
+            other => panic!("expected `return x;`, got {:?}", other),
+        };
+        assert_eq!(depth(&middle[1]), Some(1));
+        assert_eq!(depth(&inner[0]), Some(2));
+    }
+}
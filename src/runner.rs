@@ -0,0 +1,360 @@
+//! Runs Lox source from files or the REPL and reports what went wrong.
+
+use rustyline::{Editor, error::ReadlineError, history::DefaultHistory};
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ast::Stmt,
+    ast_printer::AstPrinter,
+    diagnostic::Diagnostic,
+    interpreter::Interpreter,
+    parser::Parser,
+    repl::LoxHelper,
+    resolver::Resolver,
+    scanner::{ScanErrorKind, Scanner},
+    token::TokenType,
+    value::Value,
+};
+
+/// Name REPL input goes by in diagnostics.
+const REPL_FILE: &str = "<repl>";
+
+pub struct LoxRunner {
+    /// Lives as long as the runner, so REPL lines share their state.
+    interpreter: Interpreter,
+    error_format: ErrorFormat,
+}
+
+/// How diagnostics are written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorFormat {
+    /// rustc-like messages pointing into the source.
+    Human,
+    /// One JSON object per line, for editors and CI.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RunMode {
+    File,
+    Prompt,
+}
+
+/// Why a run failed. Errors are already reported by the time it's returned,
+/// only file mode uses it to pick an exit code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RunError {
+    /// Scan, parse or resolve errors.
+    Static,
+    Runtime,
+}
+
+impl RunError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            RunError::Static => 65,
+            RunError::Runtime => 70,
+        }
+    }
+}
+
+impl LoxRunner {
+    pub fn new(error_format: ErrorFormat) -> LoxRunner {
+        LoxRunner {
+            interpreter: Interpreter::new(),
+            error_format,
+        }
+    }
+
+    pub fn run_file(&mut self, path: &Path) {
+        let contents = fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("failed to read file {:?}: {}", path, err);
+            std::process::exit(66);
+        });
+
+        if let Err(err) = self.run(&path.display().to_string(), contents, RunMode::File) {
+            std::process::exit(err.exit_code());
+        }
+    }
+
+    pub fn run_prompt(mut self) {
+        let mut editor: Editor<LoxHelper, DefaultHistory> = match Editor::new() {
+            Ok(editor) => editor,
+            Err(err) => {
+                eprintln!("failed to start line editor: {}", err);
+                return;
+            }
+        };
+
+        editor.set_helper(Some(LoxHelper::new(self.interpreter.globals_env())));
+
+        let history = history_path();
+        if let Some(path) = &history {
+            // There's no history on the first run, that's fine.
+            let _ = editor.load_history(path);
+        }
+
+        let mut input = String::new();
+
+        loop {
+            let prompt = if input.is_empty() { "> " } else { "... " };
+
+            match editor.readline(prompt) {
+                Ok(line) => {
+                    if input.is_empty() && line.trim_start().starts_with(':') {
+                        let _ = editor.add_history_entry(line.as_str());
+                        self.run_command(line.trim());
+                        if let Some(helper) = editor.helper_mut() {
+                            helper.set_globals(self.interpreter.globals_env());
+                        }
+                        continue;
+                    }
+
+                    input.push_str(&line);
+                    input.push('\n');
+
+                    // An empty line submits unfinished input as is, so there's
+                    // always a way out of the continuation prompt.
+                    let submit = line.trim().is_empty();
+                    if !submit && is_incomplete(&input) {
+                        continue;
+                    }
+
+                    let source = input.trim().to_string();
+                    input.clear();
+                    if source.is_empty() {
+                        continue;
+                    }
+
+                    let _ = editor.add_history_entry(source.as_str());
+                    // Errors are already reported, the session just goes on.
+                    let _ = self.run(REPL_FILE, source, RunMode::Prompt);
+                }
+                // Ctrl-C drops the current input, like most shells do.
+                Err(ReadlineError::Interrupted) => input.clear(),
+                Err(ReadlineError::Eof) => {
+                    println!("got EOF, closing...");
+                    break;
+                }
+                Err(err) => {
+                    eprintln!("failed to read line: {}", err);
+                    break;
+                }
+            }
+        }
+
+        if let Some(path) = &history
+            && let Err(err) = editor.save_history(path)
+        {
+            eprintln!("failed to save history to {:?}: {}", path, err);
+        }
+    }
+
+    /// Handles colon-prefixed REPL commands used to inspect the session.
+    fn run_command(&mut self, line: &str) {
+        let (command, argument) = match line.split_once(char::is_whitespace) {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (line, ""),
+        };
+
+        match command {
+            ":tokens" => {
+                let (tokens, errors) = Scanner::new(argument.to_string()).scan_tokens();
+                for token in tokens {
+                    println!("{}", token);
+                }
+                for err in errors {
+                    self.report(err.into(), REPL_FILE, argument);
+                }
+            }
+            ":ast" => {
+                let (tokens, errors) = Scanner::new(argument.to_string()).scan_tokens();
+                for err in errors {
+                    self.report(err.into(), REPL_FILE, argument);
+                }
+
+                match Parser::new_repl(tokens).parse() {
+                    Ok(statements) => {
+                        for stmt in &statements {
+                            println!("{}", AstPrinter::print_stmt(stmt));
+                        }
+                    }
+                    Err(errors) => {
+                        for err in errors {
+                            self.report(err.into(), REPL_FILE, argument);
+                        }
+                    }
+                }
+            }
+            ":env" => {
+                for (name, value) in self.interpreter.globals() {
+                    println!("{} = {}", name, value);
+                }
+            }
+            ":load" => match fs::read_to_string(argument) {
+                Ok(contents) => {
+                    let _ = self.run(argument, contents, RunMode::File);
+                }
+                Err(err) => eprintln!("failed to read file {:?}: {}", argument, err),
+            },
+            ":reset" => self.interpreter = Interpreter::new(),
+            ":help" => {
+                println!(":tokens <src>  print the tokens of <src>");
+                println!(":ast <src>     print the syntax tree of <src>");
+                println!(":env           list global variables");
+                println!(":load <file>   run a file in the current session");
+                println!(":reset         forget everything defined so far");
+            }
+            _ => println!("unknown command {}, see :help", command),
+        }
+    }
+
+    /// Runs `source`, `file` is only used to point at it in diagnostics.
+    fn run(&mut self, file: &str, source: String, mode: RunMode) -> Result<(), RunError> {
+        let (tokens, scan_errors) = Scanner::new(source.clone()).scan_tokens();
+
+        // Scan errors don't stop the run yet, so the parser and resolver get
+        // to report their problems too.
+        let mut diagnostics: Vec<Diagnostic> =
+            scan_errors.into_iter().map(Diagnostic::from).collect();
+
+        let parser = match mode {
+            RunMode::File => Parser::new(tokens),
+            RunMode::Prompt => Parser::new_repl(tokens),
+        };
+
+        let mut statements = match parser.parse() {
+            Ok(statements) => statements,
+            Err(errors) => {
+                diagnostics.extend(errors.into_iter().map(Diagnostic::from));
+                return Err(self.report_static(diagnostics, file, &source));
+            }
+        };
+
+        if let Err(errors) = Resolver::new().resolve(&mut statements) {
+            diagnostics.extend(errors.into_iter().map(Diagnostic::from));
+        }
+
+        if !diagnostics.is_empty() {
+            return Err(self.report_static(diagnostics, file, &source));
+        }
+
+        // Like a Python shell, the REPL echoes the value of a trailing
+        // expression, unless it's nil.
+        let mut echo = None;
+        if mode == RunMode::Prompt
+            && matches!(statements.last(), Some(Stmt::Expression(_)))
+            && let Some(Stmt::Expression(expr)) = statements.pop()
+        {
+            echo = Some(expr);
+        }
+
+        let result = self
+            .interpreter
+            .interpret(&statements)
+            .and_then(|_| match echo {
+                Some(expr) => self.interpreter.evaluate(&expr),
+                None => Ok(Value::Nil),
+            });
+
+        match result {
+            Ok(Value::Nil) => Ok(()),
+            Ok(value) => {
+                println!("{}", value);
+                Ok(())
+            }
+            Err(err) => {
+                self.report(err.into(), file, &source);
+                Err(RunError::Runtime)
+            }
+        }
+    }
+
+    /// Reports the errors found before running, in the order they appear
+    /// in the source rather than by the stage that found them.
+    fn report_static(
+        &self,
+        mut diagnostics: Vec<Diagnostic>,
+        file: &str,
+        source: &str,
+    ) -> RunError {
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        for diagnostic in diagnostics {
+            self.report(diagnostic, file, source);
+        }
+        RunError::Static
+    }
+
+    /// Prints a diagnostic to stderr. Human readable ones are colored if
+    /// stderr is a terminal.
+    fn report(&self, diagnostic: Diagnostic, file: &str, source: &str) {
+        match self.error_format {
+            ErrorFormat::Human => {
+                let color = io::stderr().is_terminal();
+                eprint!("{}", diagnostic.render(file, source, color));
+            }
+            ErrorFormat::Json => eprintln!("{}", diagnostic.to_json(file, source)),
+        }
+    }
+}
+
+/// The REPL keeps its history in `~/.rlox_history`.
+fn history_path() -> Option<PathBuf> {
+    env::home_dir().map(|home| home.join(".rlox_history"))
+}
+
+/// Tells whether REPL input stopped in the middle of a statement, so the
+/// prompt should keep reading lines instead of reporting an error.
+fn is_incomplete(source: &str) -> bool {
+    let (tokens, errors) = Scanner::new(source.to_string()).scan_tokens();
+    if !errors.is_empty() {
+        return errors
+            .iter()
+            .any(|err| matches!(err.kind, ScanErrorKind::UnterminatedString));
+    }
+
+    match Parser::new_repl(tokens).parse() {
+        Ok(_) => false,
+        Err(errors) => errors
+            .iter()
+            .any(|err| err.token.token_type == TokenType::Eof),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_input_runs() {
+        assert!(!is_incomplete("print 1;\n"));
+        assert!(!is_incomplete("1 + 2\n"));
+        assert!(!is_incomplete("fun f() {\n  return 1;\n}\n"));
+        assert!(!is_incomplete("\n"));
+    }
+
+    #[test]
+    fn unfinished_statements_keep_reading() {
+        assert!(is_incomplete("fun f() {\n"));
+        assert!(is_incomplete("if (true)\n"));
+        assert!(is_incomplete("print (1 +\n"));
+        assert!(is_incomplete("class A {\n  f() {}\n"));
+        assert!(is_incomplete("var a =\n"));
+    }
+
+    #[test]
+    fn unterminated_string_keeps_reading() {
+        assert!(is_incomplete("print \"multi\n"));
+        assert!(!is_incomplete("print \"multi\nline\";\n"));
+    }
+
+    #[test]
+    fn errors_before_the_end_are_reported() {
+        assert!(!is_incomplete("print 1 +;\n"));
+        assert!(!is_incomplete("var x = @\n"));
+    }
+}
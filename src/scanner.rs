@@ -1,17 +1,49 @@
+use std::fmt;
+
 use crate::{
     ast::Literal,
-    token::{Token, TokenType},
+    token::{Span, Token, TokenType},
 };
 
 #[derive(Debug, Clone)]
-pub struct ScanError(pub String);
+pub struct ScanError {
+    pub kind: ScanErrorKind,
+    pub line: i32,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ScanErrorKind {
+    UnexpectedCharacter(char),
+    UnterminatedString,
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ScanErrorKind::UnexpectedCharacter(c) => write!(f, "Unexpected character: {}", c),
+            ScanErrorKind::UnterminatedString => write!(f, "Unterminated string"),
+        }
+    }
+}
 
 pub struct Scanner {
     source: String,
     tokens: Vec<Token>,
+    errors: Vec<ScanError>,
+    /// Byte offsets of the current token and of the next character, always
+    /// on character boundaries.
     start: usize,
     current: usize,
     line: i32,
+    /// Column of the next character, counted in characters.
+    column: usize,
+    /// Line and column of `start`, tokens can span multiple lines.
+    start_line: i32,
+    start_column: usize,
+    /// Line and empty span right after the last token, where the `Eof`
+    /// token goes.
+    end_of_last_token: (i32, Span),
 }
 
 impl Scanner {
@@ -19,25 +51,43 @@ impl Scanner {
         Scanner {
             current: 0,
             line: 1,
+            column: 1,
             source,
             start: 0,
+            start_line: 1,
+            start_column: 1,
+            end_of_last_token: (
+                1,
+                Span {
+                    start: 0,
+                    end: 0,
+                    column: 1,
+                },
+            ),
             tokens: vec![],
+            errors: vec![],
         }
     }
 
-    pub fn scan_tokens(mut self) -> Result<Vec<Token>, ScanError> {
+    /// Scans the whole source. Lexical errors don't stop the scanner, each
+    /// one is recorded and leaves an `Error` token in the stream, which
+    /// always ends with an `Eof` token.
+    pub fn scan_tokens(mut self) -> (Vec<Token>, Vec<ScanError>) {
         while !self.is_end() {
-            self.start = self.current;
-            self.scan_token()?;
+            self.begin_token();
+            self.scan_token();
         }
 
+        // Errors at the end of the input, like a missing `;`, point right
+        // after the last token rather than at trailing blank lines.
+        let (line, span) = self.end_of_last_token;
         self.tokens
-            .push(Token::new(TokenType::Eof, "".into(), None, self.line));
+            .push(Token::new(TokenType::Eof, "".into(), None, line, span));
 
-        Ok(self.tokens)
+        (self.tokens, self.errors)
     }
 
-    fn scan_token(&mut self) -> Result<(), ScanError> {
+    fn scan_token(&mut self) {
         match self.advance() {
             // Simple tokens
             '(' => self.add_token(TokenType::LeftParen),
@@ -92,95 +142,125 @@ impl Scanner {
             }
 
             // Literals
-            '"' => self.string()?,
+            '"' => self.string(),
             d if d.is_ascii_digit() => self.number(),
             c if c.is_alphabetic() => self.identifier(),
 
             // Useless characters
             ' ' | '\r' | '\t' => {}
 
-            '\n' => self.line += 1,
+            '\n' => self.new_line(),
 
-            c => {
-                return Err(ScanError(format!("Unexpected character: {}", c)));
-            }
+            c => self.error(ScanErrorKind::UnexpectedCharacter(c)),
         };
-
-        Ok(())
     }
 
     fn is_end(&self) -> bool {
         self.current >= self.source.len()
     }
 
-    fn cur_char(&self) -> char {
-        self.source.chars().nth(self.current).unwrap()
+    /// Everything from the next character on. Slicing at `current` is
+    /// cheap, so looking ahead never walks the source from its start.
+    fn rest(&self) -> &str {
+        &self.source[self.current..]
     }
 
     fn advance(&mut self) -> char {
-        let c = self.cur_char();
-        self.current += 1;
+        let c = self.peek();
+        self.current += c.len_utf8();
+        self.column += 1;
         c
     }
 
     fn peek(&self) -> char {
-        if self.is_end() {
-            return '\0';
-        }
-
-        self.cur_char()
+        self.rest().chars().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if self.current >= self.source.len() {
-            return '\0';
-        }
-
-        self.source.chars().nth(self.current + 1).unwrap()
+        self.rest().chars().nth(1).unwrap_or('\0')
     }
 
     fn char_match(&mut self, expected: char) -> bool {
-        if self.is_end() || self.cur_char() != expected {
+        if self.is_end() || self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
+        self.advance();
         true
     }
 
+    fn begin_token(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column;
+    }
+
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.column = 1;
+    }
+
+    fn span(&self) -> Span {
+        Span {
+            start: self.start,
+            end: self.current,
+            column: self.start_column,
+        }
+    }
+
+    fn error(&mut self, kind: ScanErrorKind) {
+        self.errors.push(ScanError {
+            kind,
+            line: self.start_line,
+            span: self.span(),
+        });
+        self.add_token(TokenType::Error);
+    }
+
     fn add_token(&mut self, token_type: TokenType) {
-        let text = &self.source[self.start..self.current];
-        self.tokens
-            .push(Token::new(token_type, text.to_string(), None, self.line));
+        self.push_token(token_type, None);
     }
 
     fn add_token_val(&mut self, token_type: TokenType, val: Literal) {
+        self.push_token(token_type, Some(val));
+    }
+
+    fn push_token(&mut self, token_type: TokenType, literal: Option<Literal>) {
         let text = &self.source[self.start..self.current];
         self.tokens.push(Token::new(
             token_type,
             text.to_string(),
-            Some(val),
-            self.line,
+            literal,
+            self.start_line,
+            self.span(),
         ));
+
+        self.end_of_last_token = (
+            self.line,
+            Span {
+                start: self.current,
+                end: self.current,
+                column: self.column,
+            },
+        );
     }
 
-    fn string(&mut self) -> Result<(), ScanError> {
+    fn string(&mut self) {
         while self.peek() != '"' && !self.is_end() {
-            if self.peek() == '\n' {
-                self.line += 1
+            let c = self.advance();
+            if c == '\n' {
+                self.new_line();
             }
-            self.advance();
         }
 
         if self.is_end() {
-            return Err(ScanError(String::from("Unterminated string")));
+            self.error(ScanErrorKind::UnterminatedString);
+            return;
         }
 
         self.advance();
         let text = &self.source[self.start + 1..self.current - 1];
         self.add_token_val(TokenType::String, Literal::String(text.into()));
-
-        Ok(())
     }
 
     fn number(&mut self) {
@@ -214,24 +294,100 @@ impl Scanner {
     }
 }
 
+pub const KEYWORDS: [(&str, TokenType); 16] = [
+    ("and", TokenType::And),
+    ("class", TokenType::Class),
+    ("else", TokenType::Else),
+    ("false", TokenType::False),
+    ("for", TokenType::For),
+    ("fun", TokenType::Fun),
+    ("if", TokenType::If),
+    ("nil", TokenType::Nil),
+    ("or", TokenType::Or),
+    ("print", TokenType::Print),
+    ("return", TokenType::Return),
+    ("super", TokenType::Super),
+    ("this", TokenType::This),
+    ("true", TokenType::True),
+    ("var", TokenType::Var),
+    ("while", TokenType::While),
+];
+
 fn is_keyword(lexeme: &str) -> Option<TokenType> {
-    match lexeme {
-        "and" => Some(TokenType::And),
-        "class" => Some(TokenType::Class),
-        "else" => Some(TokenType::Else),
-        "false" => Some(TokenType::False),
-        "for" => Some(TokenType::For),
-        "fun" => Some(TokenType::Fun),
-        "if" => Some(TokenType::If),
-        "nil" => Some(TokenType::Nil),
-        "or" => Some(TokenType::Or),
-        "print" => Some(TokenType::Print),
-        "return" => Some(TokenType::Return),
-        "super" => Some(TokenType::Super),
-        "this" => Some(TokenType::This),
-        "true" => Some(TokenType::True),
-        "var" => Some(TokenType::Var),
-        "while" => Some(TokenType::While),
-        _ => None,
+    KEYWORDS
+        .iter()
+        .find(|(keyword, _)| *keyword == lexeme)
+        .map(|(_, token_type)| *token_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> (Vec<Token>, Vec<ScanError>) {
+        Scanner::new(source.to_string()).scan_tokens()
+    }
+
+    fn span(start: usize, end: usize, column: usize) -> Span {
+        Span { start, end, column }
+    }
+
+    #[test]
+    fn scans_non_ascii_source() {
+        let source = "var größe = \"héllo 👋\";";
+        let (tokens, errors) = scan(source);
+        assert!(errors.is_empty());
+
+        let scanned: Vec<(TokenType, &str, Span)> = tokens
+            .iter()
+            .map(|token| (token.token_type, token.lexeme.as_str(), token.span))
+            .collect();
+        assert_eq!(
+            scanned,
+            vec![
+                (TokenType::Var, "var", span(0, 3, 1)),
+                (TokenType::Identifier, "größe", span(4, 11, 5)),
+                (TokenType::Equal, "=", span(12, 13, 11)),
+                (TokenType::String, "\"héllo 👋\"", span(14, 27, 13)),
+                (TokenType::Semicolon, ";", span(27, 28, 22)),
+                (TokenType::Eof, "", span(28, 28, 23)),
+            ]
+        );
+        for token in &tokens {
+            assert_eq!(&source[token.span.start..token.span.end], token.lexeme);
+        }
+        assert_eq!(tokens[3].literal, Some(Literal::String("héllo 👋".into())));
+    }
+
+    #[test]
+    fn counts_columns_in_characters_on_each_line() {
+        let (tokens, _) = scan("// ünïcödé\n  é;");
+        assert_eq!(tokens[0].lexeme, "é");
+        assert_eq!(tokens[0].line, 2);
+        assert_eq!(tokens[0].span, span(17, 19, 3));
+        assert_eq!(tokens[1].span, span(19, 20, 4));
+    }
+
+    #[test]
+    fn reports_unexpected_non_ascii_character() {
+        let (tokens, errors) = scan("a → b");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].kind,
+            ScanErrorKind::UnexpectedCharacter('→')
+        ));
+        assert_eq!(errors[0].span, span(2, 5, 3));
+        assert_eq!(tokens[1].token_type, TokenType::Error);
+        assert_eq!(tokens[2].span, span(6, 7, 5));
+    }
+
+    #[test]
+    fn unterminated_string_runs_to_the_end() {
+        let (tokens, errors) = scan("print \"naïve");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].kind, ScanErrorKind::UnterminatedString));
+        assert_eq!(errors[0].span, span(6, 13, 7));
+        assert_eq!(tokens[1].lexeme, "\"naïve");
+        assert_eq!(tokens.last().unwrap().token_type, TokenType::Eof);
     }
 }
@@ -50,10 +50,14 @@ pub enum TokenType {
     Var,
     While,
 
+    /// Source the scanner couldn't make sense of, it's reported as a
+    /// `ScanError` as well.
+    Error,
     Eof,
     Unknown,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for TokenType {
     fn default() -> Self {
         Self::Unknown
@@ -100,6 +104,7 @@ impl fmt::Display for TokenType {
             TokenType::True => "True",
             TokenType::Var => "Var",
             TokenType::While => "While",
+            TokenType::Error => "Error",
             TokenType::Eof => "Eof",
             TokenType::Unknown => "Unknown",
         };
@@ -107,21 +112,42 @@ impl fmt::Display for TokenType {
     }
 }
 
+/// Location of a piece of source code, used to point diagnostics at it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset right after the last character.
+    pub end: usize,
+    /// 1-based column of the first character on its line, counted in
+    /// characters rather than bytes.
+    pub column: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<Literal>,
+    /// Line the token starts on.
     pub line: i32,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: Option<Literal>, line: i32) -> Self {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: String,
+        literal: Option<Literal>,
+        line: i32,
+        span: Span,
+    ) -> Self {
         Token {
             token_type,
             lexeme,
             literal,
             line,
+            span,
         }
     }
 }
@@ -0,0 +1,64 @@
+use std::{fmt, rc::Rc};
+
+use crate::{
+    ast::Literal,
+    class::{LoxClass, LoxInstance},
+    function::{LoxFunction, NativeFunction},
+};
+
+/// Everything a Lox expression can evaluate to at runtime.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Function(Rc<LoxFunction>),
+    Native(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<LoxInstance>),
+    Nil,
+}
+
+impl From<Literal> for Value {
+    fn from(literal: Literal) -> Self {
+        match literal {
+            Literal::Number(n) => Value::Number(n),
+            Literal::String(s) => Value::String(s),
+            Literal::Bool(b) => Value::Bool(b),
+            Literal::Nil => Value::Nil,
+        }
+    }
+}
+
+// Values of different types are never equal, callables, classes and
+// instances are compared by identity.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Function(l), Value::Function(r)) => Rc::ptr_eq(l, r),
+            (Value::Native(l), Value::Native(r)) => Rc::ptr_eq(l, r),
+            (Value::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
+            (Value::Instance(l), Value::Instance(r)) => Rc::ptr_eq(l, r),
+            (Value::Nil, Value::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Function(function) => write!(f, "{}", function),
+            Value::Native(native) => write!(f, "{}", native),
+            Value::Class(class) => write!(f, "{}", class),
+            Value::Instance(instance) => write!(f, "{}", instance),
+            Value::Nil => write!(f, "nil"),
+        }
+    }
+}
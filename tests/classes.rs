@@ -0,0 +1,59 @@
+mod common;
+
+use common::{assert_prints, assert_runtime_error};
+
+#[test]
+fn initializer_returns_this() {
+    assert_prints(
+        "initializer.lox",
+        "Point(1)\ntrue\ntrue\n5\n-1\ntrue\nPoint(5)\n",
+    );
+}
+
+#[test]
+fn undefined_property() {
+    assert_runtime_error(
+        "undefined_property.lox",
+        "1\n",
+        "E0301",
+        "undefined_property.lox:6:9",
+    );
+}
+
+#[test]
+fn property_of_non_instance() {
+    assert_runtime_error(
+        "property_of_non_instance.lox",
+        "before\n",
+        "E0308",
+        "property_of_non_instance.lox:3:12",
+    );
+}
+
+#[test]
+fn field_of_non_instance() {
+    assert_runtime_error(
+        "field_of_non_instance.lox",
+        "",
+        "E0309",
+        "field_of_non_instance.lox:2:7",
+    );
+}
+
+#[test]
+fn methods_are_looked_up_the_superclass_chain() {
+    assert_prints(
+        "inheritance.lox",
+        "hello from B\nhello from C via C\nB\n42\n",
+    );
+}
+
+#[test]
+fn superclass_must_be_a_class() {
+    assert_runtime_error(
+        "superclass_not_class.lox",
+        "before\n",
+        "E0310",
+        "superclass_not_class.lox:3:17",
+    );
+}
@@ -0,0 +1,51 @@
+use std::{path::Path, process::Command};
+
+/// What running `rlox` on a fixture printed, and how it exited.
+pub struct Run {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: i32,
+}
+
+/// Runs a file from `tests/fixtures`. The fixture is passed by name, so
+/// diagnostics point at e.g. `counter.lox:1:1`.
+pub fn run(fixture: &str) -> Run {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .arg(fixture)
+        .current_dir(fixtures)
+        .output()
+        .expect("failed to run rlox");
+
+    Run {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+        code: output.status.code().expect("rlox was killed by a signal"),
+    }
+}
+
+/// Asserts the fixture runs without errors and prints `expected`.
+pub fn assert_prints(fixture: &str, expected: &str) {
+    let run = run(fixture);
+    assert_eq!(run.stderr, "");
+    assert_eq!(run.stdout, expected);
+    assert_eq!(run.code, 0);
+}
+
+/// Asserts the fixture prints `expected`, then fails at runtime with the
+/// error code at `location`.
+pub fn assert_runtime_error(fixture: &str, expected: &str, code: &str, location: &str) {
+    let run = run(fixture);
+    assert_eq!(run.stdout, expected);
+    assert!(
+        run.stderr.starts_with(&format!("error[{}]", code)),
+        "unexpected stderr: {}",
+        run.stderr
+    );
+    assert!(
+        run.stderr.contains(&format!("--> {}\n", location)),
+        "unexpected stderr: {}",
+        run.stderr
+    );
+    assert_eq!(run.code, 70);
+}
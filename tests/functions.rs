@@ -0,0 +1,33 @@
+mod common;
+
+use common::{assert_prints, assert_runtime_error};
+
+#[test]
+fn closures_keep_their_own_state() {
+    assert_prints("counter.lox", "1\n2\n1\n3\n");
+}
+
+#[test]
+fn recursion() {
+    assert_prints("fib.lox", "0\n1\n1\n2\n3\n5\n8\n13\n21\n34\n");
+}
+
+#[test]
+fn return_unwinds_out_of_loops() {
+    assert_prints("return.lox", "3\nprime\nearly\nnil\n");
+}
+
+#[test]
+fn wrong_argument_count_points_at_closing_paren() {
+    assert_runtime_error(
+        "wrong_arity.lox",
+        "before\n",
+        "E0307",
+        "wrong_arity.lox:6:6",
+    );
+}
+
+#[test]
+fn unbounded_recursion_overflows_the_lox_stack() {
+    assert_runtime_error("stack_overflow.lox", "", "E0311", "stack_overflow.lox:2:23");
+}